@@ -0,0 +1,7 @@
+#!/bin/bash
+set -e
+
+RUSTFLAGS='-C link-arg=-s' cargo +stable build --target wasm32-unknown-unknown --release
+cd ..
+pwd
+cp target/wasm32-unknown-unknown/release/mock_staking_pool.wasm res/
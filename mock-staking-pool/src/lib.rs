@@ -0,0 +1,65 @@
+//! Minimal stand-in for the core staking-pool contract, only what the prize pool calls.
+//! Unstaked balance is withdrawable right away and rewards are added by hand.
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::{ValidAccountId, U128};
+use near_sdk::{env, near_bindgen, AccountId, Balance, PanicOnDefault, Promise};
+
+near_sdk::setup_alloc!();
+
+#[near_bindgen]
+#[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
+pub struct Contract {
+    staked: LookupMap<AccountId, Balance>,
+    unstaked: LookupMap<AccountId, Balance>,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new() -> Self {
+        Self {
+            staked: LookupMap::new(b"s".to_vec()),
+            unstaked: LookupMap::new(b"u".to_vec()),
+        }
+    }
+
+    #[payable]
+    pub fn deposit_and_stake(&mut self) {
+        let account_id = env::predecessor_account_id();
+        let staked = self.staked.get(&account_id).unwrap_or(0);
+        self.staked.insert(&account_id, &(staked + env::attached_deposit()));
+    }
+
+    pub fn unstake(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        let staked = self.staked.get(&account_id).unwrap_or(0);
+        assert!(staked >= amount.0, "Not enough staked balance to unstake");
+        self.staked.insert(&account_id, &(staked - amount.0));
+        let unstaked = self.unstaked.get(&account_id).unwrap_or(0);
+        self.unstaked.insert(&account_id, &(unstaked + amount.0));
+    }
+
+    pub fn withdraw(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        let unstaked = self.unstaked.get(&account_id).unwrap_or(0);
+        assert!(unstaked >= amount.0, "Not enough unstaked balance to withdraw");
+        self.unstaked.insert(&account_id, &(unstaked - amount.0));
+        Promise::new(account_id).transfer(amount.0);
+    }
+
+    pub fn get_account_staked_balance(&self, account_id: ValidAccountId) -> U128 {
+        self.staked.get(account_id.as_ref()).unwrap_or(0).into()
+    }
+
+    pub fn get_account_unstaked_balance(&self, account_id: ValidAccountId) -> U128 {
+        self.unstaked.get(account_id.as_ref()).unwrap_or(0).into()
+    }
+
+    /// Simulate staking rewards, the attached NEAR is added to the account's stake.
+    #[payable]
+    pub fn add_reward(&mut self, account_id: ValidAccountId) {
+        let staked = self.staked.get(account_id.as_ref()).unwrap_or(0);
+        self.staked.insert(account_id.as_ref(), &(staked + env::attached_deposit()));
+    }
+}
@@ -0,0 +1,7 @@
+#!/bin/bash
+set -e
+
+source ./variables.sh
+
+# 编译&升级,旧状态迁移到新的结构
+bash ./build.sh && near deploy $PRIZE_POOL_CONTRACT_ID out/prize-pool.wasm migrate '{}' --node_url $NODE_URL
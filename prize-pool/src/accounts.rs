@@ -2,7 +2,7 @@ use std::collections::{HashMap, HashSet};
 use crate::prize::{FtPrize, NftPrize};
 use crate::prize_pool::{PoolId};
 use crate::utils::{ONE_YOCTO, TokenAccountId};
-use crate::utils::{GAS_FOR_FT_TRANSFER, GAS_FOR_RESOLVE_TRANSFER};
+use crate::utils::{GAS_FOR_FT_TRANSFER, GAS_FOR_RESOLVE_TRANSFER, MAX_BATCH_WITHDRAW_TRANSFERS};
 use crate::{Contract, StorageKey};
 use crate::*;
 use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
@@ -19,6 +19,9 @@ use std::panic::catch_unwind;
 use near_sdk::env::log;
 use crate::asset::{ContractId, NftId};
 use crate::twitter_giveaway::TwitterPoolDisplay;
+use crate::storage::StoragePayer;
+use crate::requirement::Requirement;
+use crate::errors::{ContractError, fail};
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
@@ -27,6 +30,59 @@ pub struct AccountPrizePoolHistory {
     records: Vec<Record>,
 }
 
+/// All assets of one token contract inside a batch withdrawal, sent to that contract as a single receipt.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum WithdrawLeg {
+    Ft(Ft),
+    Nfts { contract_id: ContractId, nft_ids: Vec<NftId> },
+}
+
+impl WithdrawLeg {
+    /// Merge the requested assets into one leg per token contract.
+    pub fn group(items: &AssetsDTO) -> Vec<WithdrawLeg> {
+        let ft_legs = items.ft_assets.iter()
+            .map(|ft| (ft.contract_id.clone(), ft.balance.0))
+            .into_group_map()
+            .into_iter()
+            .sorted_by(|a, b| a.0.cmp(&b.0))
+            .map(|(contract_id, amounts)| WithdrawLeg::Ft(Ft { contract_id, balance: U128(amounts.iter().sum()) }));
+        let nft_legs = items.nft_assets.iter()
+            .map(|nft| (nft.contract_id.clone(), nft.nft_id.clone()))
+            .into_group_map()
+            .into_iter()
+            .sorted_by(|a, b| a.0.cmp(&b.0))
+            .map(|(contract_id, nft_ids)| WithdrawLeg::Nfts { contract_id, nft_ids: nft_ids.into_iter().unique().collect_vec() });
+        return ft_legs.chain(nft_legs).collect_vec();
+    }
+
+    pub fn transfer_count(&self) -> usize {
+        match self {
+            WithdrawLeg::Ft(_) => 1,
+            WithdrawLeg::Nfts { nft_ids, .. } => nft_ids.len(),
+        }
+    }
+
+    pub fn withdraw_from(&self, assets: &mut Assets) {
+        match self {
+            WithdrawLeg::Ft(ft) => {
+                require!(ft.balance.0 > 0, ContractError::ZeroAmount, "can not withdraw zero amount of {}", ft.contract_id);
+                assets.withdraw_ft(ft);
+            }
+            WithdrawLeg::Nfts { contract_id, nft_ids } => nft_ids.iter()
+                .for_each(|nft_id| assets.withdraw_contract_nft_id(contract_id, nft_id)),
+        }
+    }
+
+    pub fn deposit_into(&self, assets: &mut Assets) {
+        match self {
+            WithdrawLeg::Ft(ft) => assets.deposit_ft(ft),
+            WithdrawLeg::Nfts { contract_id, nft_ids } => nft_ids.iter()
+                .for_each(|nft_id| assets.deposit_contract_nft_id(contract_id, nft_id)),
+        }
+    }
+}
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub enum VAccount {
     Current(Account),
@@ -100,6 +156,55 @@ pub trait ContractMethod {
         contract_id: AccountId,
         nft_id: AccountId,
     );
+    fn withdraw_batch_callback(
+        &mut self,
+        sender_id: AccountId,
+        legs: Vec<WithdrawLeg>,
+    );
+    fn storage_balance_of_callback(
+        &mut self,
+        received_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        pool_id: PoolId,
+    );
+    fn storage_deposit_callback(
+        &mut self,
+        received_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        payer: StoragePayer,
+    );
+    fn token_gate_callback(
+        &mut self,
+        pool_id: PoolId,
+        joiner: AccountId,
+        gates: Vec<Requirement>,
+        referrer: Option<AccountId>,
+    );
+    fn on_staking_lottery_staked(
+        &mut self,
+        pool_id: PoolId,
+        account_id: AccountId,
+        amount: U128,
+    );
+    fn on_staking_lottery_unstaked(
+        &mut self,
+        pool_id: PoolId,
+        account_id: AccountId,
+        amount: U128,
+        principal: bool,
+    );
+    fn on_staking_lottery_withdrawn(
+        &mut self,
+        pool_id: PoolId,
+        account_id: AccountId,
+        amount: U128,
+    );
+    fn on_staking_lottery_balance(
+        &mut self,
+        pool_id: PoolId,
+    );
 }
 
 #[near_bindgen]
@@ -111,11 +216,7 @@ impl Contract {
         contract_id: AccountId,
         nft_id: AccountId,
     ) {
-        assert_eq!(
-            env::promise_results_count(),
-            1,
-            "withdraw_nft_callback_invalid"
-        );
+        require!(env::promise_results_count() == 1, ContractError::UnexpectedPromiseResults, "withdraw_nft_callback_invalid");
         match env::promise_result(0) {
             PromiseResult::NotReady => unreachable!(),
             PromiseResult::Successful(_) => {}
@@ -132,11 +233,7 @@ impl Contract {
         sender_id: AccountId,
         amount: U128,
     ) {
-        assert_eq!(
-            env::promise_results_count(),
-            1,
-            "withdraw_ft_callback_invalid"
-        );
+        require!(env::promise_results_count() == 1, ContractError::UnexpectedPromiseResults, "withdraw_ft_callback_invalid");
         match env::promise_result(0) {
             PromiseResult::NotReady => unreachable!(),
             PromiseResult::Successful(_) => {}
@@ -148,6 +245,28 @@ impl Contract {
         }
     }
 
+    #[private]
+    pub fn withdraw_batch_callback(
+        &mut self,
+        sender_id: AccountId,
+        legs: Vec<WithdrawLeg>,
+    ) {
+        require!(env::promise_results_count() == legs.len() as u64, ContractError::UnexpectedPromiseResults, "withdraw_batch_callback_invalid");
+        let mut account = self.internal_get_account(&sender_id);
+        legs.iter().enumerate().for_each(|(index, leg)| {
+            match env::promise_result(index as u64) {
+                PromiseResult::NotReady => unreachable!(),
+                PromiseResult::Successful(_) => {}
+                PromiseResult::Failed => {
+                    // only the legs that failed go back to the sender
+                    log!("withdraw {:?} to {} failed, refund to account", leg, sender_id);
+                    leg.deposit_into(&mut account.assets);
+                }
+            }
+        });
+        self.internal_save_account(&sender_id, account);
+    }
+
     // transfer ft to other account
     #[private]
     pub(crate) fn external_send_ft(
@@ -205,6 +324,45 @@ impl Contract {
         // memo: string|null,
     }
 
+    // transfer every leg in its own receipt, then settle all of them in one callback
+    pub(crate) fn external_send_batch(
+        &self,
+        received_id: &AccountId,
+        legs: Vec<WithdrawLeg>,
+    ) -> Promise {
+        legs.iter()
+            .map(|leg| match leg {
+                WithdrawLeg::Ft(ft) => ext_fungible_token::ft_transfer(
+                    received_id.clone(),
+                    ft.balance,
+                    None,
+                    &ft.contract_id,
+                    1,
+                    GAS_FOR_FT_TRANSFER,
+                ),
+                WithdrawLeg::Nfts { contract_id, nft_ids } => nft_ids.iter()
+                    .fold(Promise::new(contract_id.clone()), |promise, nft_id| {
+                        promise.function_call(
+                            b"nft_transfer".to_vec(),
+                            json!({
+                                "receiver_id": received_id.clone(),
+                                "token_id": nft_id.clone()
+                            }).to_string().into_bytes(),
+                            ONE_YOCTO,
+                            GAS_FOR_FT_TRANSFER)
+                    }),
+            })
+            .reduce(|all, leg| all.and(leg))
+            .unwrap_or_else(|| fail(ContractError::NothingToWithdraw, "nothing to withdraw"))
+            .then(ext_self::withdraw_batch_callback(
+                received_id.clone(),
+                legs,
+                &env::current_account_id(),
+                0,
+                GAS_FOR_RESOLVE_TRANSFER,
+            ))
+    }
+
     // todo add storage manager
     pub fn internal_get_account(&self, account_id: &AccountId) -> Account {
         return self.accounts
@@ -297,6 +455,31 @@ impl Contract {
         //3. 调外部合约transfer nft
         self.external_send_nft(&env::predecessor_account_id(), contract_id.as_ref(), &nft_id)
     }
+
+    /// Withdraw several fts and nfts at once, one receipt is sent per token contract.
+    #[payable]
+    pub fn withdraw_batch(&mut self, items: AssetsDTO) -> Promise {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        let legs = WithdrawLeg::group(&items);
+        require!(!legs.is_empty(), ContractError::NothingToWithdraw, "nothing to withdraw");
+        let transfers: usize = legs.iter().map(WithdrawLeg::transfer_count).sum();
+        require!(transfers <= MAX_BATCH_WITHDRAW_TRANSFERS, ContractError::TooManyTransfers,
+                "too many transfers in one batch: {}, max is {}", transfers, MAX_BATCH_WITHDRAW_TRANSFERS);
+
+        self.internal_use_account(&sender_id, |account| {
+            legs.iter().for_each(|leg| leg.withdraw_from(&mut account.assets));
+        });
+        self.external_send_batch(&sender_id, legs)
+    }
+
+    /// Withdraw every non-empty asset of the caller.
+    #[payable]
+    pub fn withdraw_all(&mut self) -> Promise {
+        let mut assets: AssetsDTO = self.internal_get_account(&env::predecessor_account_id()).assets.into();
+        assets.ft_assets.retain(|ft| ft.balance.0 > 0);
+        self.withdraw_batch(assets)
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -309,34 +492,84 @@ mod test_account {
     use crate::TwitterPool;
     use crate::tests::setup_contract;
     use crate::twitter_giveaway::TwitterPoolCreateParam;
+    use crate::accounts::WithdrawLeg;
+    use near_sdk::{testing_env, MockedBlockchain, PromiseResult};
+    use near_sdk::test_utils::accounts;
 
-    #[test]
-    fn test_create() {
-        const CREATE_PARAM_RAW: &str = r#"{
+    fn create_param_raw(contract_id: &str) -> String {
+        format!(r#"{{
     "name": "1",
-    "requirements": "[]",
+    "requirements": [],
     "twitter_link": "123",
     "white_list": [],
     "cover": "https://justplayproducts.com/wp-content/uploads/2020/06/78550_78551-Ryans-Mystery-Playdate-Mini-Mystery-Boxes-Call-Out-2-scaled-470x470.jpg",
     "describe": "1",
     "end_time": 1642919340000,
     "ft_prizes": [
-      {
-        "ft": {
-          "contract_id": "NEAR",
-          "balance": "00000000000000000000000000"
-        }
-      }
+      {{
+        "ft": {{
+          "contract_id": "{}",
+          "balance": "1000000000000000000000000"
+        }}
+      }}
     ],
     "join_accounts": null,
     "nft_prizes": []
-}"#;
+}}"#, contract_id)
+    }
 
+    #[test]
+    fn test_create() {
         // tests::setup_contract()
         let (mut context, mut contract) = setup_contract();
-        let param = near_sdk::serde_json::from_str(CREATE_PARAM_RAW).unwrap();
+        testing_env!(context.block_timestamp(1638790720000 * 1000000).build());
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(1000000000000000000000000));
+        let param = near_sdk::serde_json::from_str(&create_param_raw("wrap.testnet")).unwrap();
 
         let pool = contract.create_twitter_pool(param);
-        println!("{:?}", pool)
+        assert_eq!(contract.view_twitter_prize_pool(pool).prize_pool.ft_prizes.len(), 1);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[should_panic(expected = "E033_INVALID_ACCOUNT_ID")]
+    fn test_create_with_invalid_contract_id() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.block_timestamp(1638790720000 * 1000000).build());
+        let param = near_sdk::serde_json::from_str(&create_param_raw("NEAR")).unwrap();
+        contract.create_twitter_pool(param);
+    }
+
+    #[test]
+    fn test_withdraw_batch() {
+        let (mut context, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(100));
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"usdc.testnet".to_string(), &U128(50));
+        contract.internal_deposit_nft(accounts(0).as_ref(), &"nft.testnet".to_string(), &"1".to_string());
+        contract.withdraw_all();
+
+        let assets = contract.view_account_assets(accounts(0));
+        assert!(assets.ft_assets.iter().all(|ft| ft.balance.0 == 0));
+        assert!(assets.nft_assets.is_empty());
+
+        // legs are ordered by contract: usdc, wrap, then the nft contract
+        let legs = vec![
+            WithdrawLeg::Ft(Ft { contract_id: "usdc.testnet".to_string(), balance: U128(50) }),
+            WithdrawLeg::Ft(Ft { contract_id: "wrap.testnet".to_string(), balance: U128(100) }),
+            WithdrawLeg::Nfts { contract_id: "nft.testnet".to_string(), nft_ids: vec!["1".to_string()] },
+        ];
+        testing_env!(
+            context.build(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Failed, PromiseResult::Successful(vec![]), PromiseResult::Failed]
+        );
+        contract.withdraw_batch_callback(accounts(0).into(), legs);
+
+        let balance = contract.view_account_balance(accounts(0));
+        assert_eq!(balance.get("usdc.testnet").unwrap().0, 50);
+        assert_eq!(balance.get("wrap.testnet").unwrap().0, 0);
+        assert_eq!(contract.view_account_assets(accounts(0)).nft_assets.len(), 1);
+    }
+}
@@ -1,5 +1,6 @@
 use std::borrow::BorrowMut;
 use crate::*;
+use crate::errors::{ContractError, fail};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::process::id;
@@ -77,6 +78,13 @@ impl Assets {
         }
     }
 
+    pub fn deposit_assets(&mut self, assets: &Assets) {
+        assets.fts.iter().for_each(|(contract_id, amount)| self.deposit_contract_amount(contract_id, amount));
+        assets.nfts.iter().for_each(|(contract_id, ids)| {
+            ids.iter().for_each(|nft_id| self.deposit_contract_nft_id(contract_id, nft_id))
+        });
+    }
+
     pub fn deposit_ft(&mut self, ft: &Ft) {
         self.deposit_contract_amount(&ft.contract_id,&ft.balance.0);
     }
@@ -101,9 +109,8 @@ impl Assets {
     }
     pub fn withdraw_contract_amount(&mut self, contract_id: &ContractId, amount: &Amount) {
         let balance = self.fts.get(contract_id).unwrap_or(&0);
-        if *balance < *amount {
-            panic!("Fail to withdraw ft {{contract_id: {}, amount: {}}}, account balance is {}",contract_id,amount, balance);
-        }
+        require!(*balance >= *amount, ContractError::InsufficientBalance,
+                 "fail to withdraw ft {{contract_id: {}, amount: {}}}, account balance is {}", contract_id, amount, balance);
         self.fts.insert(contract_id.clone(), *balance - *amount);
     }
 
@@ -111,7 +118,8 @@ impl Assets {
         self.withdraw_contract_nft_id(&nft.contract_id,&nft.nft_id);
     }
     pub fn withdraw_contract_nft_id(&mut self, contract_id: &ContractId, nft_id: &NftId) {
-        let mut nfts = self.nfts.get_mut(contract_id).expect("nft not exist");
-        assert!(nfts.remove(nft_id),"nft not exist");
+        let nfts = self.nfts.get_mut(contract_id)
+            .unwrap_or_else(|| fail(ContractError::NftNotOwned, format!("nft {}:{} not exist", contract_id, nft_id)));
+        require!(nfts.remove(nft_id), ContractError::NftNotOwned, "nft {}:{} not exist", contract_id, nft_id);
     }
 }
\ No newline at end of file
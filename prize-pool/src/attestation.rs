@@ -0,0 +1,131 @@
+use std::convert::TryFrom;
+use crate::*;
+use crate::errors::{ContractError, fail};
+use crate::prize_pool::PoolId;
+use crate::twitter_giveaway::TwitterAccount;
+use crate::utils::get_block_milli_time;
+use ed25519_dalek::{PublicKey, Signature, Verifier};
+use near_sdk::json_types::{Base58PublicKey, Base64VecU8};
+
+/// The bytes the white list admin signs off-chain to let `account_id` join `pool_id` as `twitter_account`.
+pub fn attestation_message(
+    contract_id: &AccountId,
+    pool_id: &PoolId,
+    account_id: &AccountId,
+    twitter_account: &TwitterAccount,
+    expiry: &MilliTimeStamp,
+) -> Vec<u8> {
+    return format!("{}:{}:{}:{}:{}", contract_id, pool_id, account_id, twitter_account, expiry).into_bytes();
+}
+
+pub fn verify_attestation(public_key: &Base58PublicKey, message: &[u8], signature: &[u8]) -> bool {
+    // the first byte of Base58PublicKey is the curve type
+    let public_key = PublicKey::from_bytes(&public_key.0[1..])
+        .unwrap_or_else(|_| fail(ContractError::InvalidAttestation, "invalid attestation public key"));
+    let signature = Signature::try_from(signature)
+        .unwrap_or_else(|_| fail(ContractError::InvalidAttestation, "invalid attestation signature"));
+    return public_key.verify(message, &signature).is_ok();
+}
+
+#[near_bindgen]
+impl Contract {
+
+    pub fn set_attestation_public_key(&mut self, public_key: Base58PublicKey) {
+        let signer = env::predecessor_account_id();
+        require!(signer == self.admin || signer == self.white_list_admin, ContractError::NoAuthority, "no authority set attestation public key");
+        require!(public_key.0.len() == 33 && public_key.0[0] == 0, ContractError::InvalidAttestation, "only ed25519 public key is supported");
+        self.attestation_public_key = Some(public_key);
+    }
+
+    pub fn view_attestation_public_key(&self) -> Option<Base58PublicKey> {
+        return self.attestation_public_key.clone();
+    }
+
+    /// Join with an eligibility attestation signed by the white list admin instead of being put
+    /// into the white list by a transaction. The twitter account gets bound to the joiner contract wide.
+    pub fn join_twitter_pool_with_attestation(
+        &mut self,
+        pool_id: PoolId,
+        twitter_account: TwitterAccount,
+        expiry: MilliTimeStamp,
+        signature: Base64VecU8,
+    ) -> PromiseOrValue<bool> {
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        pool.assert_joinable();
+        require!(get_block_milli_time() <= expiry, ContractError::AttestationExpired, "attestation expired at {}", expiry);
+        let joiner = env::predecessor_account_id();
+        let public_key = self.attestation_public_key.as_ref()
+            .unwrap_or_else(|| fail(ContractError::InvalidAttestation, "attestation public key not set"));
+        let message = attestation_message(&env::current_account_id(), &pool_id, &joiner, &twitter_account, &expiry);
+        require!(verify_attestation(public_key, &message, &signature.0), ContractError::InvalidAttestation, "invalid attestation signature");
+
+        self.internal_bind_twitter_account(&joiner, &twitter_account);
+        pool.twitter_near_bind.insert(twitter_account, joiner.clone());
+        self.internal_check_and_join_twitter_pool(pool, joiner, None)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_attestation {
+    use ed25519_dalek::{ExpandedSecretKey, PublicKey, SecretKey};
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::json_types::{Base58PublicKey, Base64VecU8};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::attestation::attestation_message;
+    use crate::tests::{PoolBuilder, setup_contract};
+
+    const EXPIRY: MilliTimeStamp = 2000000;
+
+    fn sign(message: &[u8]) -> (Base58PublicKey, Base64VecU8) {
+        let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let signature = ExpandedSecretKey::from(&secret).sign(message, &public);
+        let mut public_key = vec![0u8];
+        public_key.extend_from_slice(public.as_bytes());
+        (Base58PublicKey(public_key), Base64VecU8(signature.to_bytes().to_vec()))
+    }
+
+    #[test]
+    fn test_join_with_attestation() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new().end_time(EXPIRY + 1000).build(&mut context, &mut contract);
+        contract.set_attestation_public_key(sign(b"").0);
+        let message = attestation_message(&env::current_account_id(), &pool_id, &accounts(1).into(), &"bob_twitter".to_string(), &EXPIRY);
+        let (_, signature) = sign(&message);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool_with_attestation(pool_id, "bob_twitter".to_string(), EXPIRY, signature);
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert!(pool.prize_pool.join_accounts.contains(accounts(1).as_ref()));
+        assert_eq!(pool.twitter_near_bind.get("bob_twitter").unwrap(), accounts(1).as_ref());
+        assert_eq!(contract.view_twitter_binding(None, Some(accounts(1))).unwrap().twitter_account, "bob_twitter");
+    }
+
+    #[test]
+    #[should_panic(expected = "E049_INVALID_ATTESTATION: invalid attestation signature")]
+    fn test_join_with_attestation_of_other_account() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new().end_time(EXPIRY + 1000).build(&mut context, &mut contract);
+        contract.set_attestation_public_key(sign(b"").0);
+        let message = attestation_message(&env::current_account_id(), &pool_id, &accounts(1).into(), &"bob_twitter".to_string(), &EXPIRY);
+        let (_, signature) = sign(&message);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.join_twitter_pool_with_attestation(pool_id, "bob_twitter".to_string(), EXPIRY, signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "E050_ATTESTATION_EXPIRED: attestation expired")]
+    fn test_join_with_expired_attestation() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new().end_time(EXPIRY + 1000).build(&mut context, &mut contract);
+        contract.set_attestation_public_key(sign(b"").0);
+        let message = attestation_message(&env::current_account_id(), &pool_id, &accounts(1).into(), &"bob_twitter".to_string(), &EXPIRY);
+        let (_, signature) = sign(&message);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp((EXPIRY + 1) * 1000000).build());
+        contract.join_twitter_pool_with_attestation(pool_id, "bob_twitter".to_string(), EXPIRY, signature);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::*;
+use crate::errors::{ContractError, fail};
+use crate::asset::{AssetsDTO, Ft, Nft};
+use crate::prize_pool::{PoolId, PoolStatus};
+use crate::utils::{get_block_milli_time, log_event};
+use crate::validation::MAX_PRIZES;
+use near_sdk::serde_json::json;
+
+#[near_bindgen]
+impl Contract {
+
+    /// Move the caller's prizes of a pool with claim deadline into its assets.
+    pub fn claim_prizes(&mut self, pool_id: PoolId) -> AssetsDTO {
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        let claim_deadline = pool.claim_deadline
+            .unwrap_or_else(|| fail(ContractError::ClaimNotRequired, "prizes of this pool need no claim"));
+        require!(get_block_milli_time() <= claim_deadline, ContractError::ClaimDeadline, "claim_deadline ({}) has passed", claim_deadline);
+        let account_id = env::predecessor_account_id();
+        let prizes = pool.claimable_prizes.remove(&account_id)
+            .unwrap_or_else(|| fail(ContractError::PrizeNotFound, "no prize to claim"));
+        self.internal_use_account(&account_id, |account| account.assets.deposit_assets(&prizes));
+        self.internal_save_twitter_pool(pool);
+        return prizes.into();
+    }
+
+    /// Creator takes back the prizes nobody claimed before the deadline,
+    /// they go to the rollover pool instead when there is one.
+    pub fn reclaim_unclaimed_prizes(&mut self, pool_id: PoolId) {
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        let creator_id = env::predecessor_account_id();
+        require!(pool.prize_pool.creator_id == creator_id, ContractError::NotPoolCreator, "only creator can reclaim prizes");
+        let claim_deadline = pool.claim_deadline
+            .unwrap_or_else(|| fail(ContractError::ClaimNotRequired, "prizes of this pool need no claim"));
+        require!(get_block_milli_time() > claim_deadline, ContractError::ClaimDeadline, "claim_deadline ({}) hasn't passed", claim_deadline);
+
+        // the records of winners that never claimed tell which prizes go back
+        let unclaimed_ids = pool.records.iter()
+            .filter(|e| pool.claimable_prizes.contains_key(&e.receiver))
+            .filter_map(|e| e.ft_prize.as_ref().map(|e| e.prize_id).or(e.nft_prize.as_ref().map(|e| e.prize_id)))
+            .collect_vec();
+        unclaimed_ids.iter().for_each(|prize_id| self.internal_reclaim_prize(prize_id, &creator_id));
+        let mut unclaimed = Assets::default();
+        pool.claimable_prizes.drain().for_each(|(_, prizes)| unclaimed.deposit_assets(&prizes));
+        self.internal_save_twitter_pool(pool);
+
+        let unclaimed_prizes: AssetsDTO = unclaimed.clone().into();
+        let prize_count = unclaimed_prizes.ft_assets.iter().filter(|ft| ft.balance.0 > 0).count() + unclaimed_prizes.nft_assets.len();
+        let rollover = self.internal_get_rollover_pool(&pool_id, prize_count);
+        match rollover {
+            Some(mut target) => {
+                unclaimed_prizes.ft_assets.into_iter()
+                    .filter(|ft| ft.balance.0 > 0)
+                    .for_each(|ft| {
+                        let prize = self.internal_new_ft_prize(target.prize_pool.id, Ft { contract_id: ft.contract_id, balance: ft.balance });
+                        target.prize_pool.ft_prizes.push(prize);
+                    });
+                unclaimed_prizes.nft_assets.into_iter()
+                    .for_each(|nft| {
+                        let prize = self.internal_new_nft_prize(target.prize_pool.id, Nft { contract_id: nft.contract_id, nft_id: nft.nft_id });
+                        target.prize_pool.nft_prizes.push(prize);
+                    });
+                log_event("unclaimed_prizes_rollover", json!({ "pool_id": pool_id, "rollover_pool": target.prize_pool.id }));
+                target.update_time = get_block_milli_time();
+                self.internal_save_twitter_pool(target);
+            }
+            None => {
+                log_event("unclaimed_prizes_reclaimed", json!({ "pool_id": pool_id, "creator_id": creator_id }));
+                self.internal_use_account(&creator_id, |account| account.assets.deposit_assets(&unclaimed));
+            }
+        }
+    }
+
+    // rollover pool only counts when it belongs to the same creator and can still draw `prize_count` more prizes,
+    // a published scratch card has fixed its winning slots and a recurring pool only draws per round
+    fn internal_get_rollover_pool(&self, pool_id: &PoolId, prize_count: usize) -> Option<TwitterPool> {
+        let pool = self.internal_get_twitter_pool(pool_id);
+        return pool.rollover_pool
+            .and_then(|rollover_id| self.twitter_prize_pools.get(&rollover_id))
+            .and_then(VPool::twitter_pool)
+            .map(|mut target| { target.refresh_status(); target })
+            .filter(|target| target.prize_pool.creator_id == pool.prize_pool.creator_id
+                && (target.scratch_card.is_none() || target.status == PoolStatus::PENDING)
+                && target.recurrence.is_none()
+                && target.prize_pool.ft_prizes.len() + target.prize_pool.nft_prizes.len() + prize_count <= MAX_PRIZES
+                && (target.status == PoolStatus::PENDING
+                    || ((target.status == PoolStatus::SCHEDULED || target.status == PoolStatus::ONGOING) && get_block_milli_time() < target.end_time)));
+    }
+
+    pub fn view_claimable_prizes(&self, pool_id: PoolId, account_id: ValidAccountId) -> AssetsDTO {
+        return self.internal_get_twitter_pool(&pool_id)
+            .claimable_prizes
+            .get(account_id.as_ref())
+            .map(|prizes| prizes.clone())
+            .unwrap_or_default()
+            .into();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_claim {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use crate::{Contract, PoolId};
+    use crate::prize::PrizeStatus;
+    use crate::scratch_card::ScratchCardParam;
+    use crate::tests::{PoolBuilder, setup_contract};
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
+
+    #[test]
+    fn test_claim_prizes() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1)])
+            .with(|param| param.claim_deadline = Some(2000000))
+            .drawn()
+            .build(&mut context, &mut contract);
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet"), None);
+        assert_eq!(contract.view_claimable_prizes(pool_id, accounts(1)).ft_assets[0].balance.0, 100);
+
+        contract.claim_prizes(pool_id);
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 100);
+        assert!(contract.view_claimable_prizes(pool_id, accounts(1)).ft_assets.is_empty());
+    }
+
+    #[test]
+    fn test_reclaim_unclaimed_prizes() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1)])
+            .with(|param| param.claim_deadline = Some(2000000))
+            .drawn()
+            .build(&mut context, &mut contract);
+
+        // 2000000 ms is the claim deadline
+        testing_env!(context.predecessor_account_id(accounts(0)).block_timestamp(2000001 * 1000000).build());
+        contract.reclaim_unclaimed_prizes(pool_id);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 100);
+        assert!(contract.view_claimable_prizes(pool_id, accounts(1)).ft_assets.is_empty());
+        let prize_id = contract.view_twitter_prize_pool(pool_id).records[0].ft_prize.as_ref().unwrap().prize_id;
+        let prize = contract.view_prize(prize_id);
+        assert_eq!(prize.status, PrizeStatus::REFUNDED);
+        assert_eq!(prize.receiver, Some(accounts(0).to_string()));
+    }
+
+    fn unclaimed_pool(context: &mut VMContextBuilder, contract: &mut Contract, rollover_pool: PoolId) -> PoolId {
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1)])
+            .with(|param| {
+                param.claim_deadline = Some(2000000);
+                param.rollover_pool = Some(rollover_pool);
+            })
+            .drawn()
+            .build(context, contract);
+        testing_env!(context.predecessor_account_id(accounts(0)).block_timestamp(2000001 * 1000000).build());
+        pool_id
+    }
+
+    #[test]
+    fn test_rollover_unclaimed_prizes() {
+        let (mut context, mut contract) = setup_contract();
+        let target_id = contract.create_twitter_pool(TwitterPoolCreateParam { name: Some("next".to_string()), ..Default::default() });
+        let pool_id = unclaimed_pool(&mut context, &mut contract, target_id);
+        contract.reclaim_unclaimed_prizes(pool_id);
+        assert_eq!(contract.view_twitter_prize_pool(target_id).prize_pool.ft_prizes[0].ft.balance.0, 100);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").map(|e| e.0).unwrap_or(0), 0);
+    }
+
+    #[test]
+    fn test_no_rollover_into_published_scratch_card() {
+        let (mut context, mut contract) = setup_contract();
+        let target_id = PoolBuilder::new()
+            .end_time(3000000)
+            .ft_prize(100, None)
+            .with(|param| param.scratch_card = Some(ScratchCardParam { total_entries: 10 }))
+            .build(&mut context, &mut contract);
+        let pool_id = unclaimed_pool(&mut context, &mut contract, target_id);
+        contract.reclaim_unclaimed_prizes(pool_id);
+        // winning slots of the scratch card are fixed, the creator gets the prizes back
+        assert_eq!(contract.view_twitter_prize_pool(target_id).prize_pool.ft_prizes.len(), 1);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "E052_CLAIM_DEADLINE")]
+    fn test_claim_after_deadline() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1)])
+            .with(|param| param.claim_deadline = Some(2000000))
+            .drawn()
+            .build(&mut context, &mut contract);
+        testing_env!(context.block_timestamp(2000001 * 1000000).build());
+        contract.claim_prizes(pool_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "E051_CLAIM_NOT_REQUIRED")]
+    fn test_claim_from_pool_without_deadline() {
+        let (_, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(1700000),
+            ..Default::default()
+        });
+        contract.claim_prizes(pool_id);
+    }
+}
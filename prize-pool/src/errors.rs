@@ -0,0 +1,133 @@
+use std::fmt;
+
+/// Failures a frontend can map, the code is the stable prefix of the panic message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContractError {
+    PoolNotFound,
+    WrongPoolType,
+    NotPoolCreator,
+    InvalidPoolStatus,
+    PoolNotStarted,
+    PoolEnded,
+    InvalidTimeRange,
+    InvalidRecurrence,
+    PrizeNotFound,
+    InsufficientBalance,
+    NftNotOwned,
+    ZeroAmount,
+    NothingToWithdraw,
+    TooManyTransfers,
+    UnexpectedPromiseResults,
+    DrawTimeNotReached,
+    NotInWhiteList,
+    InvalidMerkleProof,
+    InvalidWeight,
+    NoAuthority,
+    EmptyName,
+    FieldTooLong,
+    InvalidNftPrize,
+    InvalidAccountId,
+    TooManyPrizes,
+    InvalidWinners,
+    SoldOut,
+    WrongPayment,
+    AlreadyJoined,
+    JoinPending,
+    InvalidMessage,
+    InvalidRaffleParam,
+    TicketLimit,
+    StakingPoolInUse,
+    DepositTooSmall,
+    UnstakeLocked,
+    TwitterAccountBound,
+    BindingCooldown,
+    TwitterAccountNotBound,
+    InvalidAttestation,
+    AttestationExpired,
+    ClaimNotRequired,
+    ClaimDeadline,
+    InvalidFee,
+    TemplateNotFound,
+    InvalidRequirement,
+    NoStateToMigrate,
+    WithdrawalTooSmall,
+    InvalidScratchCard,
+    InvalidStages,
+}
+
+impl ContractError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ContractError::PoolNotFound => "E001_POOL_NOT_FOUND",
+            ContractError::WrongPoolType => "E002_WRONG_POOL_TYPE",
+            ContractError::NotPoolCreator => "E003_NOT_POOL_CREATOR",
+            ContractError::InvalidPoolStatus => "E004_INVALID_POOL_STATUS",
+            ContractError::PoolNotStarted => "E005_POOL_NOT_STARTED",
+            ContractError::PoolEnded => "E006_POOL_ENDED",
+            ContractError::InvalidTimeRange => "E007_INVALID_TIME_RANGE",
+            ContractError::InvalidRecurrence => "E008_INVALID_RECURRENCE",
+            ContractError::PrizeNotFound => "E009_PRIZE_NOT_FOUND",
+            ContractError::InsufficientBalance => "E010_INSUFFICIENT_BALANCE",
+            ContractError::NftNotOwned => "E011_NFT_NOT_OWNED",
+            ContractError::ZeroAmount => "E012_ZERO_AMOUNT",
+            ContractError::NothingToWithdraw => "E013_NOTHING_TO_WITHDRAW",
+            ContractError::TooManyTransfers => "E014_TOO_MANY_TRANSFERS",
+            ContractError::UnexpectedPromiseResults => "E015_UNEXPECTED_PROMISE_RESULTS",
+            ContractError::DrawTimeNotReached => "E016_DRAW_TIME_NOT_REACHED",
+            ContractError::NotInWhiteList => "E020_NOT_IN_WHITE_LIST",
+            ContractError::InvalidMerkleProof => "E021_INVALID_MERKLE_PROOF",
+            ContractError::InvalidWeight => "E022_INVALID_WEIGHT",
+            ContractError::NoAuthority => "E023_NO_AUTHORITY",
+            ContractError::EmptyName => "E030_EMPTY_NAME",
+            ContractError::FieldTooLong => "E031_FIELD_TOO_LONG",
+            ContractError::InvalidNftPrize => "E032_INVALID_NFT_PRIZE",
+            ContractError::InvalidAccountId => "E033_INVALID_ACCOUNT_ID",
+            ContractError::TooManyPrizes => "E034_TOO_MANY_PRIZES",
+            ContractError::InvalidWinners => "E035_INVALID_WINNERS",
+            ContractError::SoldOut => "E036_SOLD_OUT",
+            ContractError::WrongPayment => "E037_WRONG_PAYMENT",
+            ContractError::AlreadyJoined => "E038_ALREADY_JOINED",
+            ContractError::JoinPending => "E039_JOIN_PENDING",
+            ContractError::InvalidMessage => "E040_INVALID_MESSAGE",
+            ContractError::InvalidRaffleParam => "E041_INVALID_RAFFLE_PARAM",
+            ContractError::TicketLimit => "E042_TICKET_LIMIT",
+            ContractError::StakingPoolInUse => "E043_STAKING_POOL_IN_USE",
+            ContractError::DepositTooSmall => "E044_DEPOSIT_TOO_SMALL",
+            ContractError::UnstakeLocked => "E045_UNSTAKE_LOCKED",
+            ContractError::TwitterAccountBound => "E046_TWITTER_ACCOUNT_BOUND",
+            ContractError::BindingCooldown => "E047_BINDING_COOLDOWN",
+            ContractError::TwitterAccountNotBound => "E048_TWITTER_ACCOUNT_NOT_BOUND",
+            ContractError::InvalidAttestation => "E049_INVALID_ATTESTATION",
+            ContractError::AttestationExpired => "E050_ATTESTATION_EXPIRED",
+            ContractError::ClaimNotRequired => "E051_CLAIM_NOT_REQUIRED",
+            ContractError::ClaimDeadline => "E052_CLAIM_DEADLINE",
+            ContractError::InvalidFee => "E053_INVALID_FEE",
+            ContractError::TemplateNotFound => "E054_TEMPLATE_NOT_FOUND",
+            ContractError::InvalidRequirement => "E055_INVALID_REQUIREMENT",
+            ContractError::NoStateToMigrate => "E056_NO_STATE_TO_MIGRATE",
+            ContractError::WithdrawalTooSmall => "E057_WITHDRAWAL_TOO_SMALL",
+            ContractError::InvalidScratchCard => "E058_INVALID_SCRATCH_CARD",
+            ContractError::InvalidStages => "E059_INVALID_STAGES",
+        }
+    }
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// The one place failures panic, the message is `<code>: <detail>`.
+pub fn fail(error: ContractError, detail: impl fmt::Display) -> ! {
+    panic!("{}: {}", error.code(), detail)
+}
+
+/// `assert!` that fails with a `ContractError`.
+macro_rules! require {
+    ($cond:expr, $error:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::errors::fail($error, format!($($arg)+));
+        }
+    };
+}
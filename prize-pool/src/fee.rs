@@ -0,0 +1,176 @@
+use crate::*;
+use crate::errors::ContractError;
+use crate::asset::Ft;
+use crate::utils::FEE_DIVISOR;
+use near_sdk::assert_one_yocto;
+
+/// Platform fee, all fees are credited to the assets of `treasury_id`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeConfig {
+    pub treasury_id: AccountId,
+    // taken from every ft prize at draw time, in bps
+    pub ft_fee_bps: u32,
+    // flat fee per nft prize, paid by the creator when the nft is put into a pool
+    pub nft_fee: Option<Ft>,
+}
+
+impl FeeConfig {
+    pub fn new(treasury_id: AccountId) -> Self {
+        FeeConfig {
+            treasury_id,
+            ft_fee_bps: 0,
+            nft_fee: None,
+        }
+    }
+
+    pub fn ft_fee(&self, amount: Amount) -> Amount {
+        return amount * self.ft_fee_bps as u128 / FEE_DIVISOR as u128;
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    // split the fee off a ft prize, returns what's left for the winner
+    pub(crate) fn internal_split_ft_fee(&self, ft: &Ft, fees: &mut Assets) -> Ft {
+        let fee = self.fee_config.ft_fee(ft.balance.0);
+        fees.deposit_contract_amount(&ft.contract_id, &fee);
+        return Ft { contract_id: ft.contract_id.clone(), balance: U128(ft.balance.0 - fee) };
+    }
+
+    pub(crate) fn internal_charge_nft_fee(&mut self, payer: &AccountId, nft_count: usize) {
+        if let Some(nft_fee) = self.fee_config.nft_fee.clone() {
+            if nft_count == 0 || nft_fee.balance.0 == 0 { return; }
+            let amount = nft_fee.balance.0 * nft_count as u128;
+            self.internal_use_account(payer, |account| {
+                account.assets.withdraw_contract_amount(&nft_fee.contract_id, &amount);
+            });
+            self.internal_deposit_ft(&self.fee_config.treasury_id.clone(), &nft_fee.contract_id, &U128(amount));
+        }
+    }
+
+    pub(crate) fn internal_deposit_fees(&mut self, fees: &Assets) {
+        if fees.fts.values().all(|amount| *amount == 0) { return; }
+        let treasury_id = self.fee_config.treasury_id.clone();
+        self.internal_use_account(&treasury_id, |account| account.assets.deposit_assets(fees));
+    }
+
+    pub fn set_fee_config(&mut self, fee_config: FeeConfig) {
+        require!(env::predecessor_account_id() == self.admin, ContractError::NoAuthority, "only admin can set fee config");
+        require!(fee_config.ft_fee_bps <= FEE_DIVISOR, ContractError::InvalidFee, "ft_fee_bps can't be more than {}", FEE_DIVISOR);
+        self.fee_config = fee_config;
+    }
+
+    pub fn view_fee_config(&self) -> FeeConfig {
+        return self.fee_config.clone();
+    }
+
+    /// Send collected fees from the treasury assets to the treasury account.
+    #[payable]
+    pub fn withdraw_fees(&mut self, token_id: ValidAccountId, amount: U128) -> Promise {
+        assert_one_yocto();
+        require!(env::predecessor_account_id() == self.admin, ContractError::NoAuthority, "only admin can withdraw fees");
+        let treasury_id = self.fee_config.treasury_id.clone();
+        self.internal_use_account(&treasury_id, |account| {
+            account.assets.withdraw_contract_amount(token_id.as_ref(), &amount.0);
+        });
+        self.external_send_ft(&treasury_id, token_id.as_ref(), &amount)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_fee {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::asset::{Ft, Nft};
+    use crate::fee::FeeConfig;
+    use crate::prize::{FtPrizeCreateParam, NftPrizeCreateParam};
+    use crate::tests::setup_contract;
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
+
+    #[test]
+    fn test_ft_fee_at_draw() {
+        let (mut context, mut contract) = setup_contract();
+        contract.set_fee_config(FeeConfig { treasury_id: accounts(2).into(), ft_fee_bps: 500, nft_fee: None });
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128::from(1000));
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            describe: None,
+            cover: None,
+            end_time: Some(1700000),
+            white_list: Some(vec![accounts(1).into()]),
+            requirements: None,
+            ft_prizes: Some(vec![FtPrizeCreateParam {
+                ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128::from(1000) },
+                winners: None
+            }]),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        testing_env!(context.block_timestamp(1700000 * 1000000).build());
+        contract.pools_prize_draw();
+
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 950);
+        assert_eq!(contract.view_account_balance(accounts(2)).get("wrap.testnet").unwrap().0, 50);
+    }
+
+    #[test]
+    fn test_no_ft_fee_on_unwon_prizes() {
+        let (mut context, mut contract) = setup_contract();
+        contract.set_fee_config(FeeConfig { treasury_id: accounts(2).into(), ft_fee_bps: 500, nft_fee: None });
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128::from(1000));
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(1700000),
+            white_list: Some(vec![accounts(1).into()]),
+            ft_prizes: Some(vec![FtPrizeCreateParam {
+                ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128::from(1000) },
+                winners: Some(2)
+            }]),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        testing_env!(context.block_timestamp(1700000 * 1000000).build());
+        contract.pools_prize_draw();
+
+        // only the share won by the joiner pays the fee, the other one goes back whole
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 475);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 500);
+        assert_eq!(contract.view_account_balance(accounts(2)).get("wrap.testnet").unwrap().0, 25);
+    }
+
+    #[test]
+    fn test_nft_fee_on_update() {
+        let (_, mut contract) = setup_contract();
+        let nft_fee = Ft { contract_id: "wrap.testnet".to_string(), balance: U128::from(10) };
+        contract.set_fee_config(FeeConfig { treasury_id: accounts(2).into(), ft_fee_bps: 0, nft_fee: Some(nft_fee) });
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128::from(30));
+        let nft = |nft_id: &str| NftPrizeCreateParam { nft: Nft { contract_id: "nft.testnet".to_string(), nft_id: nft_id.to_string() } };
+        contract.internal_deposit_nft(accounts(0).as_ref(), &"nft.testnet".to_string(), &"1".to_string());
+        contract.internal_deposit_nft(accounts(0).as_ref(), &"nft.testnet".to_string(), &"2".to_string());
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            nft_prizes: Some(vec![nft("1")]),
+            ..Default::default()
+        });
+        contract.update_twitter_pool(TwitterPoolCreateParam { nft_prizes: Some(vec![nft("1"), nft("2")]), ..Default::default() }, pool_id);
+
+        // the kept nft isn't charged twice
+        assert_eq!(contract.view_account_balance(accounts(2)).get("wrap.testnet").unwrap().0, 20);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "E053_INVALID_FEE")]
+    fn test_fee_over_divisor() {
+        let (_, mut contract) = setup_contract();
+        contract.set_fee_config(FeeConfig { treasury_id: accounts(2).into(), ft_fee_bps: 10001, nft_fee: None });
+    }
+}
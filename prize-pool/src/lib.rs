@@ -1,4 +1,4 @@
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use std::convert::{TryFrom};
 use std::fmt;
 
@@ -7,23 +7,49 @@ use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
 use near_contract_standards::non_fungible_token::{TokenId};
 use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
 use near_contract_standards::non_fungible_token::NonFungibleToken;
-use near_sdk::{AccountId, BorshStorageKey, env, log, near_bindgen, PanicOnDefault, Promise, PromiseOrValue, Timestamp};
+use near_sdk::{AccountId, Balance, BorshStorageKey, env, log, near_bindgen, PanicOnDefault, Promise, PromiseOrValue, Timestamp};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{LazyOption, LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector};
-use near_sdk::json_types::{U128, U64, ValidAccountId};
+use near_sdk::json_types::{Base58PublicKey, U128, U64, ValidAccountId};
 use near_sdk::serde::{Deserialize, Serialize};
 
 use crate::accounts::{Account, VAccount};
 use crate::asset::Assets;
+use crate::errors::{ContractError, fail};
+use crate::fee::FeeConfig;
+use crate::raffle::TokenReceiverMessage;
 use crate::prize_pool::{CountDownDrawPrize, DrawPrize, PoolId, PrizeDrawTime, PrizePool, Record, VPool};
-use crate::twitter_giveaway::TwitterPool;
+use crate::twitter_giveaway::{TwitterAccount, TwitterPool};
+use crate::twitter_binding::{DEFAULT_TWITTER_BINDING_COOLDOWN, TwitterBinding};
+use crate::template::{PoolTemplate, TemplateId};
+use crate::prize::{PrizeId, PrizeInfo};
 
+#[macro_use]
+pub mod errors;
 pub mod prize;
 pub mod prize_pool;
 pub mod accounts;
 pub mod utils;
 pub mod asset;
 pub mod twitter_giveaway;
+pub mod storage;
+pub mod claim;
+pub mod fee;
+pub mod merkle;
+pub mod attestation;
+pub mod twitter_binding;
+pub mod requirement;
+pub mod raffle;
+pub mod staking_lottery;
+pub mod recurrence;
+pub mod template;
+pub mod validation;
+pub mod sponsor;
+pub mod mystery_box;
+pub mod scratch_card;
+pub mod stage;
+pub mod referral;
+pub mod migration;
 
 near_sdk::setup_alloc!();
 
@@ -47,6 +73,13 @@ pub(crate) enum StorageKey {
     AccountFts {account_id: AccountId},
     AccountNfts{account_id: AccountId},
     AccountPools {account_id: AccountId},
+    TwitterBindings,
+    NearTwitterBindings,
+    TwitterUnbindTime,
+    NearUnbindTime,
+    StakingPoolLotteries,
+    PoolTemplates,
+    Prizes,
 }
 // static ID: AtomicU64= AtomicU64::new(0);
 
@@ -98,10 +131,29 @@ pub struct Contract {
     // pub prize_pools: UnorderedMap<PoolId,PrizePool>,
     pub twitter_prize_pools: UnorderedMap<PoolId, VPool>,
     pub pool_queue: Vec<PrizeDrawTime>,// BinaryHeap<PrizeDrawTime> someday,it didn't work
+    // last pool id handed out
     pub pool_id: u64,
+    // last prize id handed out, prize ids are unique across pools
+    pub prize_id: u64,
+    pub prizes: LookupMap<PrizeId, PrizeInfo>,
     pub white_list_admin: AccountId,
     pub admin: AccountId,
-    pub records: LookupMap<PoolId, Record>
+    pub records: LookupMap<PoolId, Record>,
+    // platform NEAR budget for registering prize receivers on token contracts
+    pub storage_budget: Balance,
+    pub fee_config: FeeConfig,
+    // ed25519 key of the white list admin, verifies off-chain eligibility attestations
+    pub attestation_public_key: Option<Base58PublicKey>,
+    // contract wide twitter <-> near account binding, both directions
+    pub twitter_bindings: LookupMap<TwitterAccount, TwitterBinding>,
+    pub near_twitter_bindings: LookupMap<AccountId, TwitterAccount>,
+    pub twitter_unbind_time: LookupMap<TwitterAccount, MilliTimeStamp>,
+    pub near_unbind_time: LookupMap<AccountId, MilliTimeStamp>,
+    pub twitter_binding_cooldown: MilliTimeStamp,
+    // staking pool -> the lottery delegating to it
+    pub staking_pool_lotteries: LookupMap<AccountId, PoolId>,
+    // named pool templates of every creator
+    pub pool_templates: LookupMap<AccountId, HashMap<TemplateId, PoolTemplate>>,
 }
 
 
@@ -116,20 +168,38 @@ impl Contract {
             twitter_prize_pools: UnorderedMap::new(StorageKey::TwitterPools),
             pool_queue: vec![],
             pool_id: 0,
+            prize_id: 0,
+            prizes: LookupMap::new(StorageKey::Prizes),
             white_list_admin: white_list_admin.into(),
             admin: env::predecessor_account_id(),
-            records: LookupMap::new(StorageKey::Records)
+            records: LookupMap::new(StorageKey::Records),
+            storage_budget: 0,
+            fee_config: FeeConfig::new(env::predecessor_account_id()),
+            attestation_public_key: None,
+            twitter_bindings: LookupMap::new(StorageKey::TwitterBindings),
+            near_twitter_bindings: LookupMap::new(StorageKey::NearTwitterBindings),
+            twitter_unbind_time: LookupMap::new(StorageKey::TwitterUnbindTime),
+            near_unbind_time: LookupMap::new(StorageKey::NearUnbindTime),
+            twitter_binding_cooldown: DEFAULT_TWITTER_BINDING_COOLDOWN,
+            staking_pool_lotteries: LookupMap::new(StorageKey::StakingPoolLotteries),
+            pool_templates: LookupMap::new(StorageKey::PoolTemplates),
         }
     }
 
     #[private]
-    pub fn next_id(&mut self)->u64{
+    pub fn next_pool_id(&mut self)->u64{
         self.pool_id=self.pool_id+1;
         return self.pool_id
     }
 
+    #[private]
+    pub fn next_prize_id(&mut self)->u64{
+        self.prize_id=self.prize_id+1;
+        return self.prize_id
+    }
+
     pub fn clear(&mut self) {
-        assert_eq!(env::predecessor_account_id(),"xsb.testnet");
+        require!(env::predecessor_account_id() == "xsb.testnet", ContractError::NoAuthority, "only xsb.testnet can clear pools");
         // self.prize_pools.clear();
         self.twitter_prize_pools.clear();
         self.pool_queue.clear();
@@ -159,8 +229,25 @@ impl FungibleTokenReceiver for Contract {
     ) -> PromiseOrValue<U128> {
         log!("ft on transfer,sender_id is {},amount is {},msg is {}",sender_id,amount.0,msg);
         let token_in = env::predecessor_account_id();
-        self.internal_deposit_ft(sender_id.as_ref(), &token_in, &amount);
-        return PromiseOrValue::Value(U128(0));
+        if msg.is_empty() {
+            self.internal_deposit_ft(sender_id.as_ref(), &token_in, &amount);
+            return PromiseOrValue::Value(U128(0));
+        }
+        let message: TokenReceiverMessage = near_sdk::serde_json::from_str(&msg)
+            .unwrap_or_else(|_| fail(ContractError::InvalidMessage, format!("illegal msg: {}", msg)));
+        match message {
+            TokenReceiverMessage::BuyRaffleTickets { pool_id, count } => {
+                let cost = self.internal_buy_raffle_tickets(pool_id, sender_id.as_ref(), &token_in, count);
+                require!(amount.0 >= cost, ContractError::WrongPayment, "tickets cost {}", cost);
+                // the token contract refunds the change
+                return PromiseOrValue::Value(U128(amount.0 - cost));
+            }
+            TokenReceiverMessage::OpenMysteryBox { pool_id } => {
+                let cost = self.internal_open_mystery_box(pool_id, sender_id.as_ref(), &token_in);
+                require!(amount.0 >= cost, ContractError::WrongPayment, "mystery box costs {}", cost);
+                return PromiseOrValue::Value(U128(amount.0 - cost));
+            }
+        }
     }
 }
 
@@ -198,7 +285,9 @@ pub mod tests {
     use accounts::Account;
 
     use crate::*;
-    use crate::prize::FtPrize;
+    use crate::asset::Ft;
+    use crate::prize::{FtPrize, FtPrizeCreateParam, PrizeStatus};
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
     use crate::utils::ONE_YOCTO;
 
     use super::*;
@@ -235,6 +324,83 @@ pub mod tests {
         (context, contract)
     }
 
+    /// Pool of accounts(0) for module tests, the ft prizes are deposited to the creator before creating.
+    pub struct PoolBuilder {
+        pub param: TwitterPoolCreateParam,
+        joiners: Vec<ValidAccountId>,
+        drawn: bool,
+    }
+
+    impl PoolBuilder {
+        pub fn new() -> Self {
+            PoolBuilder {
+                param: TwitterPoolCreateParam {
+                    name: Some("pool".to_string()),
+                    end_time: Some(2000000),
+                    ..Default::default()
+                },
+                joiners: vec![],
+                drawn: false,
+            }
+        }
+
+        pub fn end_time(mut self, end_time: MilliTimeStamp) -> Self {
+            self.param.end_time = Some(end_time);
+            self
+        }
+
+        pub fn ft_prize(mut self, balance: Balance, winners: Option<u32>) -> Self {
+            self.param.ft_prizes.get_or_insert_with(Vec::new).push(FtPrizeCreateParam {
+                ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(balance) },
+                winners,
+            });
+            self
+        }
+
+        pub fn white_list(mut self, accounts: Vec<ValidAccountId>) -> Self {
+            self.param.white_list.get_or_insert_with(Vec::new).extend(accounts.into_iter().map(|e| e.into()));
+            self
+        }
+
+        /// White listed accounts that join once the pool is published.
+        pub fn joiners(mut self, joiners: Vec<ValidAccountId>) -> Self {
+            self = self.white_list(joiners.clone());
+            self.joiners = joiners;
+            self
+        }
+
+        /// Draw the pool at its end_time after the joins.
+        pub fn drawn(mut self) -> Self {
+            self.drawn = true;
+            self
+        }
+
+        pub fn with(mut self, f: impl FnOnce(&mut TwitterPoolCreateParam)) -> Self {
+            f(&mut self.param);
+            self
+        }
+
+        /// Create and publish the pool, the context is left with the last joiner as predecessor.
+        pub fn build(self, context: &mut VMContextBuilder, contract: &mut Contract) -> PoolId {
+            testing_env!(context.predecessor_account_id(accounts(0)).build());
+            for prize in self.param.ft_prizes.iter().flatten() {
+                contract.internal_deposit_ft(accounts(0).as_ref(), &prize.ft.contract_id, &prize.ft.balance);
+            }
+            let end_time = self.param.end_time.unwrap();
+            let pool_id = contract.create_twitter_pool(self.param);
+            contract.publish_pool(pool_id);
+            for joiner in self.joiners {
+                testing_env!(context.predecessor_account_id(joiner).build());
+                contract.join_twitter_pool(pool_id, None, None, None);
+            }
+            if self.drawn {
+                testing_env!(context.block_timestamp(end_time * 1000000).build());
+                contract.pools_prize_draw();
+            }
+            pool_id
+        }
+    }
+
     const FT_STR: &str = r#"{"FT":{"contract":"someone_ft","sum":0}}"#;
     const NFT_STR: &str = r#"{"NFT":{"contract":"someone_nft","id":"some nft"}}"#;
 
@@ -269,7 +435,27 @@ pub mod tests {
 
     #[test]
     fn next_id_test() {
-
+        let (_, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &WRAP_TOKEN.to_string(), &U128(300));
+        let prize = |balance: u128| FtPrizeCreateParam { ft: Ft { contract_id: WRAP_TOKEN.to_string(), balance: U128(balance) }, winners: None };
+        let first = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            ft_prizes: Some(vec![prize(100), prize(100)]),
+            ..Default::default()
+        });
+        let second = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            ft_prizes: Some(vec![prize(100)]),
+            ..Default::default()
+        });
+        assert_eq!((first, second), (1, 2));
+
+        let info = contract.view_prize(3);
+        assert_eq!(info.pool_id, second);
+        assert_eq!(info.ft.unwrap().balance.0, 100);
+        assert_eq!(info.status, PrizeStatus::ESCROWED);
+        contract.delete_twitter_prize_pool(second);
+        assert_eq!(contract.view_prize(3).status, PrizeStatus::REFUNDED);
     }
 
     // #[test]
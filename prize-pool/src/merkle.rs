@@ -0,0 +1,96 @@
+use std::convert::TryInto;
+use near_sdk::{env, AccountId, CryptoHash};
+
+/// Leaf of an eligible account: `sha256(account_id)`, or `sha256("{account_id}:{weight}")` for weighted entries.
+pub fn merkle_leaf(account_id: &AccountId, weight: Option<u32>) -> CryptoHash {
+    let data = match weight {
+        Some(weight) => format!("{}:{}", account_id, weight),
+        None => account_id.clone(),
+    };
+    return sha256(data.as_bytes());
+}
+
+/// Parent of two nodes, the pair is sorted before hashing so proofs need no left/right flags.
+pub fn merkle_parent(a: &CryptoHash, b: &CryptoHash) -> CryptoHash {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut data = first.to_vec();
+    data.extend_from_slice(second);
+    return sha256(&data);
+}
+
+pub fn verify_merkle_proof(root: &CryptoHash, leaf: CryptoHash, proof: &[CryptoHash]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| merkle_parent(&node, sibling));
+    return computed == *root;
+}
+
+fn sha256(data: &[u8]) -> CryptoHash {
+    return env::sha256(data).as_slice().try_into().unwrap();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_merkle {
+    use near_sdk::{testing_env, AccountId, CryptoHash, MockedBlockchain};
+    use near_sdk::test_utils::VMContextBuilder;
+    use crate::merkle::{merkle_leaf, verify_merkle_proof};
+
+    // tree of ENTRIES computed off-chain with python's hashlib, laid out like merkletreejs
+    // `new MerkleTree(leaves, sha256, { sortPairs: true })` with the leaves of `merkle_leaf`
+    const ROOT: &str = "92a050dcaa1abd919e3a7038de99e838ff93f5dd6c060232f48441b49232b0da";
+    const ENTRIES: [(&str, Option<u32>, &[&str]); 5] = [
+        ("alice.near", None, &[
+            "45361d5c10913cfa4e7d180f9bb38b6902324e3618fe0e425f19e718461e4b09",
+            "f4bdb43f0e97147f09b4b73a66e8499d9dcdd8d9d9e2d7f2262831ec6d2bf0db",
+            "8132e5eee0ce39099fedb40dd33593ca193d2081dda23b6f2706e0f7f20b78e0",
+        ]),
+        ("bob.near", Some(3), &[
+            "2dd5dda540767b3a1aa33544bcba38042f4df6de9bddb46798b29481c842c558",
+            "f4bdb43f0e97147f09b4b73a66e8499d9dcdd8d9d9e2d7f2262831ec6d2bf0db",
+            "8132e5eee0ce39099fedb40dd33593ca193d2081dda23b6f2706e0f7f20b78e0",
+        ]),
+        ("carol.near", None, &[
+            "3d0c1dd6ff77bb249c23a4e43e4a7a037aa1cc23bb9e963bb2653daa5fa1c0d7",
+            "7140ef88bcdf6a3c25d9369df5a18d921252e8f178284f338c6eeeda2b173b1d",
+            "8132e5eee0ce39099fedb40dd33593ca193d2081dda23b6f2706e0f7f20b78e0",
+        ]),
+        ("dave.near", Some(2), &[
+            "de30b47e961fb091f5d3e83e1fd3d6599bc6478b30de6303775496f596d6ae4d",
+            "7140ef88bcdf6a3c25d9369df5a18d921252e8f178284f338c6eeeda2b173b1d",
+            "8132e5eee0ce39099fedb40dd33593ca193d2081dda23b6f2706e0f7f20b78e0",
+        ]),
+        // the odd leaf is carried up unhashed, so it only needs the sibling at the top
+        ("eve.near", None, &[
+            "1e67be1d422c25de0932e4fd14708609adb6b94d952830683d0750df417f2f38",
+        ]),
+    ];
+
+    fn hash(hex: &str) -> CryptoHash {
+        let mut hash = CryptoHash::default();
+        for (index, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).unwrap();
+        }
+        hash
+    }
+
+    #[test]
+    fn test_leaves() {
+        testing_env!(VMContextBuilder::new().build());
+        assert_eq!(merkle_leaf(&"alice.near".to_string(), None), hash("2dd5dda540767b3a1aa33544bcba38042f4df6de9bddb46798b29481c842c558"));
+        assert_eq!(merkle_leaf(&"bob.near".to_string(), Some(3)), hash("45361d5c10913cfa4e7d180f9bb38b6902324e3618fe0e425f19e718461e4b09"));
+    }
+
+    #[test]
+    fn test_verify_proofs() {
+        testing_env!(VMContextBuilder::new().build());
+        let root = hash(ROOT);
+        for (account_id, weight, proof) in ENTRIES.iter() {
+            let account_id: AccountId = account_id.to_string();
+            let proof = proof.iter().map(|e| hash(e)).collect::<Vec<_>>();
+            assert!(verify_merkle_proof(&root, merkle_leaf(&account_id, *weight), &proof));
+            // a different weight is a different leaf
+            assert!(!verify_merkle_proof(&root, merkle_leaf(&account_id, Some(weight.unwrap_or(1) + 1)), &proof));
+        }
+        let proof = ENTRIES[0].2.iter().map(|e| hash(e)).collect::<Vec<_>>();
+        assert!(!verify_merkle_proof(&root, merkle_leaf(&"mallory.near".to_string(), None), &proof));
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+use crate::*;
+use crate::asset::{Ft, Nft};
+use crate::errors::{ContractError, fail};
+use crate::fee::FeeConfig;
+use crate::prize::{FtPrize, NftPrize, PrizeStatus};
+use crate::prize_pool::PoolStatus;
+use crate::utils::log_event;
+use near_sdk::serde_json::json;
+
+// layouts the contract was deployed with before the prize registry, keep them as they were stored
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyRecord {
+    pub time: MilliTimeStamp,
+    pub ft_prize: Option<FtPrize>,
+    pub nft_prize: Option<NftPrize>,
+    pub receiver: AccountId,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyPrizePool {
+    pub id: PoolId,
+    pub creator_id: AccountId,
+    pub ft_prizes: Vec<FtPrize>,
+    pub nft_prizes: Vec<NftPrize>,
+    pub join_accounts: HashSet<AccountId>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyTwitterPool {
+    pub name: String,
+    pub describe: String,
+    pub cover: String,
+    pub prize_pool: LegacyPrizePool,
+    pub status: PoolStatus,
+    pub end_time: MilliTimeStamp,
+    pub create_time: MilliTimeStamp,
+    pub update_time: MilliTimeStamp,
+    pub white_list: HashSet<AccountId>,
+    pub requirements: Option<String>,
+    pub twitter_near_bind: HashMap<TwitterAccount, AccountId>,
+    pub twitter_link: String,
+    pub records: Vec<LegacyRecord>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum LegacyVPool {
+    TwitterPool(LegacyTwitterPool),
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyContract {
+    pub accounts: LookupMap<AccountId, VAccount>,
+    pub twitter_prize_pools: UnorderedMap<PoolId, LegacyVPool>,
+    pub pool_queue: Vec<PrizeDrawTime>,
+    // pools and prizes took their ids from this one counter
+    pub pool_id: u64,
+    pub white_list_admin: AccountId,
+    pub admin: AccountId,
+    pub records: LookupMap<PoolId, Record>,
+}
+
+impl From<LegacyRecord> for Record {
+    fn from(record: LegacyRecord) -> Self {
+        Record {
+            time: record.time,
+            ft_prize: record.ft_prize,
+            nft_prize: record.nft_prize,
+            receiver: record.receiver,
+            sponsor: None,
+        }
+    }
+}
+
+impl From<LegacyTwitterPool> for TwitterPool {
+    fn from(pool: LegacyTwitterPool) -> Self {
+        TwitterPool {
+            name: pool.name,
+            describe: pool.describe,
+            cover: pool.cover,
+            prize_pool: PrizePool {
+                id: pool.prize_pool.id,
+                creator_id: pool.prize_pool.creator_id,
+                ft_prizes: pool.prize_pool.ft_prizes,
+                nft_prizes: pool.prize_pool.nft_prizes,
+                join_accounts: pool.prize_pool.join_accounts,
+                join_weights: HashMap::new(),
+            },
+            status: pool.status,
+            start_time: None,
+            end_time: pool.end_time,
+            create_time: pool.create_time,
+            update_time: pool.update_time,
+            white_list: pool.white_list,
+            // requirements were free text, only the ones that parse as the new list are kept
+            requirements: pool.requirements
+                .and_then(|e| near_sdk::serde_json::from_str(&e).ok())
+                .unwrap_or_default(),
+            twitter_near_bind: pool.twitter_near_bind,
+            twitter_link: pool.twitter_link,
+            records: pool.records.into_iter().map_into().collect_vec(),
+            auto_transfer: false,
+            auto_register: false,
+            storage_budget: 0,
+            claim_deadline: None,
+            rollover_pool: None,
+            claimable_prizes: Default::default(),
+            white_list_root: None,
+            pending_joins: Default::default(),
+            recurrence: None,
+            sponsors: vec![],
+            prize_sponsors: Default::default(),
+            scratch_card: None,
+            stages: vec![],
+            referrals: Default::default(),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    /// Move the state of the contract deployed before the prize registry to the current layout.
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate() -> Self {
+        let mut legacy: LegacyContract = env::state_read()
+            .unwrap_or_else(|| fail(ContractError::NoStateToMigrate, "no state to migrate"));
+        let pools = legacy.twitter_prize_pools.to_vec();
+        legacy.twitter_prize_pools.clear();
+
+        let mut contract = Contract {
+            accounts: legacy.accounts,
+            twitter_prize_pools: UnorderedMap::new(StorageKey::TwitterPools),
+            pool_queue: legacy.pool_queue,
+            pool_id: legacy.pool_id,
+            prize_id: legacy.pool_id,
+            prizes: LookupMap::new(StorageKey::Prizes),
+            white_list_admin: legacy.white_list_admin,
+            admin: legacy.admin.clone(),
+            records: legacy.records,
+            storage_budget: 0,
+            fee_config: FeeConfig::new(legacy.admin),
+            attestation_public_key: None,
+            twitter_bindings: LookupMap::new(StorageKey::TwitterBindings),
+            near_twitter_bindings: LookupMap::new(StorageKey::NearTwitterBindings),
+            twitter_unbind_time: LookupMap::new(StorageKey::TwitterUnbindTime),
+            near_unbind_time: LookupMap::new(StorageKey::NearUnbindTime),
+            twitter_binding_cooldown: DEFAULT_TWITTER_BINDING_COOLDOWN,
+            staking_pool_lotteries: LookupMap::new(StorageKey::StakingPoolLotteries),
+            pool_templates: LookupMap::new(StorageKey::PoolTemplates),
+        };
+        for (pool_id, pool) in pools {
+            let pool: TwitterPool = match pool { LegacyVPool::TwitterPool(pool) => pool.into() };
+            contract.internal_register_legacy_prizes(&pool);
+            contract.twitter_prize_pools.insert(&pool_id, &pool.into());
+        }
+        log_event("contract_migrated", json!({ "pool_count": contract.twitter_prize_pools.len() }));
+        contract
+    }
+
+    /// Prizes still in a pool are in escrow, the ones in its records were awarded.
+    fn internal_register_legacy_prizes(&mut self, pool: &TwitterPool) {
+        let pool_id = pool.prize_pool.id;
+        let escrowed = pool.status != PoolStatus::FINISHED && pool.status != PoolStatus::DELETED;
+        let mut register = |prize_id: PrizeId, ft: Option<Ft>, nft: Option<Nft>, status: PrizeStatus, receiver: Option<AccountId>| {
+            self.prizes.insert(&prize_id, &PrizeInfo { prize_id, pool_id, ft, nft, status, receiver });
+        };
+        if escrowed {
+            pool.prize_pool.ft_prizes.iter().for_each(|e| register(e.prize_id, Some(e.ft.clone()), None, PrizeStatus::ESCROWED, None));
+            pool.prize_pool.nft_prizes.iter().for_each(|e| register(e.prize_id, None, Some(e.nft.clone()), PrizeStatus::ESCROWED, None));
+        }
+        for record in pool.records.iter() {
+            if let Some(prize) = &record.ft_prize {
+                register(prize.prize_id, Some(prize.ft.clone()), None, PrizeStatus::AWARDED, Some(record.receiver.clone()));
+            }
+            if let Some(prize) = &record.nft_prize {
+                register(prize.prize_id, None, Some(prize.nft.clone()), PrizeStatus::AWARDED, Some(record.receiver.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_migration {
+    use std::collections::{HashMap, HashSet};
+    use near_sdk::env;
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::asset::Ft;
+    use crate::migration::{LegacyContract, LegacyPrizePool, LegacyRecord, LegacyTwitterPool, LegacyVPool};
+    use crate::prize::{FtPrize, PrizeStatus};
+    use crate::prize_pool::PoolStatus;
+    use crate::tests::setup_contract;
+
+    fn legacy_pool(pool_id: PoolId, status: PoolStatus, prize_id: PrizeId) -> LegacyTwitterPool {
+        let prize = FtPrize { prize_id, ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(100) } };
+        let finished = status == PoolStatus::FINISHED;
+        LegacyTwitterPool {
+            name: "pool".to_string(),
+            describe: "".to_string(),
+            cover: "".to_string(),
+            prize_pool: LegacyPrizePool {
+                id: pool_id,
+                creator_id: accounts(0).into(),
+                ft_prizes: if finished { vec![] } else { vec![prize.clone()] },
+                nft_prizes: vec![],
+                join_accounts: HashSet::new(),
+            },
+            status,
+            end_time: 1700000,
+            create_time: 1638790,
+            update_time: 1638790,
+            white_list: HashSet::new(),
+            requirements: Some("follow us".to_string()),
+            twitter_near_bind: HashMap::new(),
+            twitter_link: "".to_string(),
+            records: if finished {
+                vec![LegacyRecord { time: 1700000, ft_prize: Some(prize), nft_prize: None, receiver: accounts(1).into() }]
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    #[test]
+    fn test_migrate() {
+        let (_, _) = setup_contract();
+        let mut legacy = LegacyContract {
+            accounts: LookupMap::new(StorageKey::Accounts),
+            twitter_prize_pools: UnorderedMap::new(StorageKey::TwitterPools),
+            pool_queue: vec![PrizeDrawTime(1, 1700000)],
+            pool_id: 4,
+            white_list_admin: "xsb.near".to_string(),
+            admin: accounts(0).into(),
+            records: LookupMap::new(StorageKey::Records),
+        };
+        // the legacy counter gave pool 1 prize 2 and pool 3 prize 4
+        legacy.twitter_prize_pools.insert(&1, &LegacyVPool::TwitterPool(legacy_pool(1, PoolStatus::ONGOING, 2)));
+        legacy.twitter_prize_pools.insert(&3, &LegacyVPool::TwitterPool(legacy_pool(3, PoolStatus::FINISHED, 4)));
+        env::state_write(&legacy);
+
+        let mut contract = Contract::migrate();
+        let pool = contract.view_twitter_prize_pool(1);
+        assert_eq!(pool.status, PoolStatus::ONGOING);
+        assert!(pool.requirements.is_empty());
+        assert_eq!(contract.view_prize(2).status, PrizeStatus::ESCROWED);
+        let prize = contract.view_prize(4);
+        assert_eq!(prize.status, PrizeStatus::AWARDED);
+        assert_eq!(prize.receiver, Some(accounts(1).to_string()));
+        assert_eq!(contract.view_twitter_prize_pool(3).records[0].sponsor, None);
+        assert_eq!(contract.view_prize_pool_queue().len(), 1);
+        // new ids continue after the legacy ones
+        assert_eq!(contract.next_prize_id(), 5);
+        assert_eq!(contract.next_pool_id(), 5);
+
+        // a legacy pool can still be deleted, its prize goes back to the creator
+        contract.delete_twitter_prize_pool(1);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 100);
+    }
+}
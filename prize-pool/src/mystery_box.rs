@@ -0,0 +1,313 @@
+use crate::*;
+use crate::errors::{ContractError, fail};
+use crate::asset::ContractId;
+use crate::prize::{FtPrize, FtPrizeCreateParam, NftPrizeCreateParam, Prize};
+use crate::prize_pool::PoolStatus;
+use crate::raffle::NEAR_TOKEN_ID;
+use crate::utils::{get_block_milli_time, log_event, random_number_with_nonce, vec_random_with_seed};
+use crate::validation::{assert_valid_param, prize_count, validate_info, validate_prizes};
+use near_sdk::assert_one_yocto;
+use near_sdk::serde_json::json;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MysteryBox {
+    pub name: String,
+    pub describe: String,
+    pub cover: String,
+    // the prizes of prize_pool are the inventory that is not opened yet
+    pub prize_pool: PrizePool,
+    pub status: PoolStatus,
+    pub create_time: MilliTimeStamp,
+    pub price_token: ContractId,
+    pub price: U128,
+    pub sold: u32,
+    pub proceeds: U128,
+    pub records: Vec<Record>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MysteryBoxCreateParam {
+    pub name: Option<String>,
+    pub describe: Option<String>,
+    pub cover: Option<String>,
+    pub price_token: ContractId,
+    pub price: U128,
+    pub ft_prizes: Option<Vec<FtPrizeCreateParam>>,
+    pub nft_prizes: Option<Vec<NftPrizeCreateParam>>,
+}
+
+impl From<MysteryBox> for VPool {
+    fn from(pool: MysteryBox) -> Self {
+        Self::MysteryBox(pool)
+    }
+}
+
+impl MysteryBox {
+    pub fn remaining(&self) -> u32 {
+        return (self.prize_pool.ft_prizes.len() + self.prize_pool.nft_prizes.len()) as u32;
+    }
+
+    /// Take a random prize out of the inventory, each remaining prize is equally likely.
+    fn take_random_prize(&mut self, seed: u64) -> Option<Prize> {
+        let ft_count = self.prize_pool.ft_prizes.len();
+        let mut indexes = (0..ft_count + self.prize_pool.nft_prizes.len()).collect_vec();
+        let index = vec_random_with_seed(&mut indexes, seed)?;
+        if index < ft_count {
+            return Some(Prize::FT_PRIZE(self.prize_pool.ft_prizes.remove(index)));
+        }
+        return Some(Prize::NFT_PRIZE(self.prize_pool.nft_prizes.remove(index - ft_count)));
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    pub(crate) fn internal_get_mystery_box(&self, id: &PoolId) -> MysteryBox {
+        match self.twitter_prize_pools.get(id).unwrap_or_else(|| fail(ContractError::PoolNotFound, format!("pool {} not exist", id))) {
+            VPool::MysteryBox(pool) => pool,
+            _ => fail(ContractError::WrongPoolType, format!("pool {} is not a mystery box", id))
+        }
+    }
+
+    pub(crate) fn internal_save_mystery_box(&mut self, pool: MysteryBox) {
+        self.twitter_prize_pools.insert(&pool.prize_pool.id.clone(), &pool.into());
+    }
+
+    /// Mystery boxes sell right away until the inventory is sold out, there is no draw.
+    #[payable]
+    pub fn create_mystery_box(&mut self, param: MysteryBoxCreateParam) -> PoolId {
+        assert_one_yocto();
+        assert_valid_param(validate_info(&param.name, &param.describe, &param.cover, true));
+        require!(param.price.0 > 0, ContractError::ZeroAmount, "price must be positive");
+        let ft_prizes = param.ft_prizes.clone().unwrap_or_default();
+        let nft_prizes = param.nft_prizes.clone().unwrap_or_default();
+        assert_valid_param(validate_prizes(&ft_prizes, &nft_prizes));
+        let count = prize_count(&ft_prizes, &nft_prizes);
+        require!(count > 0, ContractError::PrizeNotFound, "mystery box needs prizes");
+
+        let creator_id = env::predecessor_account_id();
+        let pool_id = self.next_pool_id();
+        self.internal_use_account(&creator_id, |account| {
+            ft_prizes.iter().for_each(|x| account.assets.withdraw_contract_amount(&x.ft.contract_id, &x.ft.balance.0));
+            nft_prizes.iter().for_each(|x| account.assets.withdraw_nft(&x.nft));
+        });
+        self.internal_charge_nft_fee(&creator_id, nft_prizes.len());
+
+        let mut prize_pool = PrizePool::new(pool_id, &creator_id);
+        prize_pool.ft_prizes = self.internal_new_ft_prizes(pool_id, &ft_prizes);
+        prize_pool.nft_prizes = nft_prizes.iter().map(|e| self.internal_new_nft_prize(pool_id, e.nft.clone())).collect_vec();
+        let pool = MysteryBox {
+            name: param.name.unwrap_or_default(),
+            describe: param.describe.unwrap_or_default(),
+            cover: param.cover.unwrap_or_default(),
+            prize_pool,
+            status: PoolStatus::ONGOING,
+            create_time: get_block_milli_time(),
+            price_token: param.price_token,
+            price: param.price,
+            sold: 0,
+            proceeds: U128(0),
+            records: vec![],
+        };
+        self.internal_save_mystery_box(pool);
+        log_event("mystery_box_created", json!({
+            "pool_id": pool_id,
+            "creator_id": creator_id,
+            "count": count,
+        }));
+        return pool_id;
+    }
+
+    /// Open a box sold for NEAR, the attached deposit must be exactly the price.
+    #[payable]
+    pub fn open_mystery_box(&mut self, pool_id: PoolId) -> Record {
+        let price = self.internal_get_mystery_box(&pool_id).price.0;
+        require!(env::attached_deposit() == price, ContractError::WrongPayment, "attached deposit must be {}", price);
+        self.internal_open_mystery_box(pool_id, &env::predecessor_account_id(), &NEAR_TOKEN_ID.to_string());
+        return self.internal_get_mystery_box(&pool_id).records.pop().unwrap();
+    }
+
+    /// Hands a random prize to the buyer and returns the price, the caller makes sure it has been paid.
+    pub(crate) fn internal_open_mystery_box(&mut self, pool_id: PoolId, buyer: &AccountId, token_id: &ContractId) -> Balance {
+        let mut pool = self.internal_get_mystery_box(&pool_id);
+        require!(pool.status == PoolStatus::ONGOING, ContractError::SoldOut, "mystery box {} is closed", pool_id);
+        require!(&pool.price_token == token_id, ContractError::WrongPayment, "boxes are sold in {}", pool.price_token);
+
+        let nonce = [pool_id.to_le_bytes().to_vec(), pool.sold.to_le_bytes().to_vec(), buyer.as_bytes().to_vec()].concat();
+        let prize = pool.take_random_prize(random_number_with_nonce(&nonce))
+            .unwrap_or_else(|| fail(ContractError::SoldOut, format!("mystery box {} is sold out", pool_id)));
+        let mut record = Record {
+            time: get_block_milli_time(),
+            ft_prize: None,
+            nft_prize: None,
+            receiver: buyer.clone(),
+            sponsor: None,
+        };
+        match &prize {
+            Prize::NFT_PRIZE(nft_prize) => {
+                self.internal_award_prize(&nft_prize.prize_id, buyer);
+                self.internal_deposit_nft(buyer, &nft_prize.nft.contract_id, &nft_prize.nft.nft_id);
+                record.nft_prize = Some(nft_prize.clone());
+            }
+            Prize::FT_PRIZE(ft_prize) => {
+                self.internal_award_prize(&ft_prize.prize_id, buyer);
+                let mut fees = Assets::default();
+                let ft_prize = FtPrize { prize_id: ft_prize.prize_id, ft: self.internal_split_ft_fee(&ft_prize.ft, &mut fees) };
+                self.internal_deposit_ft(buyer, &ft_prize.ft.contract_id, &ft_prize.ft.balance);
+                self.internal_deposit_fees(&fees);
+                record.ft_prize = Some(ft_prize);
+            }
+        }
+        pool.records.push(record);
+
+        let price = pool.price.0;
+        pool.sold += 1;
+        pool.proceeds = U128(pool.proceeds.0 + price);
+        let creator_id = pool.prize_pool.creator_id.clone();
+        let price_token = pool.price_token.clone();
+        self.internal_pay_proceeds(&creator_id, &price_token, price);
+        if pool.remaining() == 0 {
+            pool.status = PoolStatus::FINISHED;
+            log_event("mystery_box_sold_out", json!({ "pool_id": pool_id, "proceeds": pool.proceeds }));
+        }
+        self.internal_save_mystery_box(pool);
+        log_event("mystery_box_opened", json!({
+            "pool_id": pool_id,
+            "account_id": buyer,
+        }));
+        return price;
+    }
+
+    /// Stop selling and give the unopened prizes back to the creator.
+    #[payable]
+    pub fn close_mystery_box(&mut self, pool_id: PoolId) {
+        assert_one_yocto();
+        let mut pool = self.internal_get_mystery_box(&pool_id);
+        require!(pool.prize_pool.creator_id == env::predecessor_account_id(), ContractError::NotPoolCreator, "only the creator can close the mystery box");
+        require!(pool.status == PoolStatus::ONGOING, ContractError::InvalidPoolStatus, "mystery box {} is already closed", pool_id);
+        let creator_id = pool.prize_pool.creator_id.clone();
+        for prize in pool.prize_pool.ft_prizes.drain(..) {
+            self.internal_deposit_ft(&creator_id, &prize.ft.contract_id, &prize.ft.balance);
+            self.internal_refund_prize(&prize.prize_id, &creator_id);
+        }
+        for prize in pool.prize_pool.nft_prizes.drain(..) {
+            self.internal_deposit_nft(&creator_id, &prize.nft.contract_id, &prize.nft.nft_id);
+            self.internal_refund_prize(&prize.prize_id, &creator_id);
+        }
+        pool.status = PoolStatus::FINISHED;
+        self.internal_save_mystery_box(pool);
+    }
+
+    pub fn view_mystery_box(&self, pool_id: PoolId) -> MysteryBox {
+        return self.internal_get_mystery_box(&pool_id);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_mystery_box {
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::asset::{Ft, Nft};
+    use crate::mystery_box::MysteryBoxCreateParam;
+    use crate::prize::{FtPrizeCreateParam, NftPrizeCreateParam};
+    use crate::prize_pool::PoolStatus;
+    use crate::raffle::NEAR_TOKEN_ID;
+    use crate::tests::setup_contract;
+    use crate::utils::ONE_NEAR;
+
+    fn box_param(price_token: &str) -> MysteryBoxCreateParam {
+        MysteryBoxCreateParam {
+            name: Some("box".to_string()),
+            describe: None,
+            cover: None,
+            price_token: price_token.to_string(),
+            price: U128(ONE_NEAR),
+            ft_prizes: Some(vec![FtPrizeCreateParam { ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(300) }, winners: Some(2) }]),
+            nft_prizes: None,
+        }
+    }
+
+    #[test]
+    fn test_open_until_sold_out() {
+        let (mut context, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        let pool_id = contract.create_mystery_box(box_param(NEAR_TOKEN_ID));
+        assert_eq!(contract.view_mystery_box(pool_id).remaining(), 2);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(ONE_NEAR).build());
+        contract.open_mystery_box(pool_id);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.open_mystery_box(pool_id);
+
+        let pool = contract.view_mystery_box(pool_id);
+        assert_eq!(pool.status, PoolStatus::FINISHED);
+        assert_eq!(pool.sold, 2);
+        assert_eq!(pool.proceeds.0, 2 * ONE_NEAR);
+        assert_eq!(pool.records.len(), 2);
+        let won: u128 = [accounts(1), accounts(2)].iter()
+            .map(|e| contract.view_account_balance(e.clone()).get("wrap.testnet").unwrap().0).sum();
+        assert_eq!(won, pool.records.iter().map(|e| e.ft_prize.as_ref().unwrap().ft.balance.0).sum());
+    }
+
+    #[test]
+    #[should_panic(expected = "E036_SOLD_OUT")]
+    fn test_open_sold_out_box() {
+        let (mut context, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        let pool_id = contract.create_mystery_box(box_param("wrap.testnet"));
+
+        testing_env!(context.predecessor_account_id(ValidAccountId::try_from("wrap.testnet").unwrap()).build());
+        let msg = format!("{{\"OpenMysteryBox\":{{\"pool_id\":{}}}}}", pool_id);
+        match contract.ft_on_transfer(accounts(1), U128(2 * ONE_NEAR), msg.clone()) {
+            PromiseOrValue::Value(refund) => assert_eq!(refund.0, ONE_NEAR),
+            _ => panic!("opening a box should refund the change"),
+        }
+        contract.ft_on_transfer(accounts(1), U128(ONE_NEAR), msg.clone());
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 2 * ONE_NEAR);
+        contract.ft_on_transfer(accounts(1), U128(ONE_NEAR), msg);
+    }
+
+    #[test]
+    #[should_panic(expected = "E035_INVALID_WINNERS")]
+    fn test_box_with_zero_winners() {
+        let (_, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        let mut param = box_param(NEAR_TOKEN_ID);
+        param.ft_prizes.as_mut().unwrap()[0].winners = Some(0);
+        contract.create_mystery_box(param);
+    }
+
+    #[test]
+    #[should_panic(expected = "E012_ZERO_AMOUNT")]
+    fn test_box_with_zero_ft_prize() {
+        let (_, mut contract) = setup_contract();
+        let mut param = box_param(NEAR_TOKEN_ID);
+        param.ft_prizes.as_mut().unwrap()[0].ft.balance = U128(0);
+        contract.create_mystery_box(param);
+    }
+
+    #[test]
+    #[should_panic(expected = "E033_INVALID_ACCOUNT_ID")]
+    fn test_box_with_invalid_contract_id() {
+        let (_, mut contract) = setup_contract();
+        let mut param = box_param(NEAR_TOKEN_ID);
+        param.ft_prizes.as_mut().unwrap()[0].ft.contract_id = "NEAR".to_string();
+        contract.create_mystery_box(param);
+    }
+
+    #[test]
+    #[should_panic(expected = "E032_INVALID_NFT_PRIZE")]
+    fn test_box_with_duplicate_nft() {
+        let (_, mut contract) = setup_contract();
+        let nft = NftPrizeCreateParam { nft: Nft { contract_id: "nft.testnet".to_string(), nft_id: "1".to_string() } };
+        let mut param = box_param(NEAR_TOKEN_ID);
+        param.ft_prizes = None;
+        param.nft_prizes = Some(vec![nft.clone(), nft]);
+        contract.create_mystery_box(param);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::*;
 use crate::asset::*;
+use crate::errors::{ContractError, fail};
 use near_sdk::collections::{UnorderedMap, Vector};
 use near_sdk::json_types::{U128, ValidAccountId};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
@@ -38,6 +39,114 @@ pub struct FtPrize{
 #[serde(crate = "near_sdk::serde")]
 pub struct FtPrizeCreateParam {
     pub ft: Ft,
+    // share ft evenly among this many winners, a single winner when None
+    pub winners: Option<u32>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize,Serialize,Deserialize,Clone,Debug,Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PrizesParam {
+    pub ft_prizes: Vec<FtPrizeCreateParam>,
+    pub nft_prizes: Vec<NftPrizeCreateParam>,
+}
+
+impl FtPrizeCreateParam {
+    pub fn winner_count(&self) -> u32 {
+        self.winners.unwrap_or(1)
+    }
+
+    /// Amount of every share, the rounding dust goes to the first one.
+    pub fn shares(&self) -> Vec<Balance> {
+        let winners = self.winner_count() as u128;
+        let share = self.ft.balance.0 / winners;
+        let dust = self.ft.balance.0 % winners;
+        (0..winners).map(|index| if index == 0 { share + dust } else { share }).collect()
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PrizeStatus {
+    // held by the contract until the pool is drawn
+    ESCROWED,
+    AWARDED,
+    // went back to whoever funded it
+    REFUNDED,
+}
+
+/// Where a prize lives and what became of it, kept for every prize id.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PrizeInfo {
+    pub prize_id: PrizeId,
+    pub pool_id: PoolId,
+    pub ft: Option<Ft>,
+    pub nft: Option<Nft>,
+    pub status: PrizeStatus,
+    pub receiver: Option<AccountId>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// One prize per share, so every winner of a shared prize gets its own record.
+    pub(crate) fn internal_new_ft_prizes(&mut self, pool_id: PoolId, params: &[FtPrizeCreateParam]) -> Vec<FtPrize> {
+        let mut prizes = vec![];
+        for param in params {
+            for share in param.shares() {
+                prizes.push(self.internal_new_ft_prize(pool_id, Ft { contract_id: param.ft.contract_id.clone(), balance: U128(share) }));
+            }
+        }
+        prizes
+    }
+
+    pub(crate) fn internal_new_ft_prize(&mut self, pool_id: PoolId, ft: Ft) -> FtPrize {
+        let prize_id = self.next_prize_id();
+        self.prizes.insert(&prize_id, &PrizeInfo { prize_id, pool_id, ft: Some(ft.clone()), nft: None, status: PrizeStatus::ESCROWED, receiver: None });
+        FtPrize { prize_id, ft }
+    }
+
+    pub(crate) fn internal_new_nft_prize(&mut self, pool_id: PoolId, nft: Nft) -> NftPrize {
+        let prize_id = self.next_prize_id();
+        self.prizes.insert(&prize_id, &PrizeInfo { prize_id, pool_id, ft: None, nft: Some(nft.clone()), status: PrizeStatus::ESCROWED, receiver: None });
+        NftPrize { prize_id, nft }
+    }
+
+    pub(crate) fn internal_award_prize(&mut self, prize_id: &PrizeId, receiver: &AccountId) {
+        if let Some(mut prize) = self.prizes.get(prize_id) {
+            prize.status = PrizeStatus::AWARDED;
+            prize.receiver = Some(receiver.clone());
+            self.prizes.insert(prize_id, &prize);
+        }
+    }
+
+    pub(crate) fn is_prize_escrowed(&self, prize_id: &PrizeId) -> bool {
+        return self.prizes.get(prize_id).map(|e| e.status == PrizeStatus::ESCROWED).unwrap_or(false);
+    }
+
+    /// Only a prize still in escrow can go back to its funder.
+    pub(crate) fn internal_refund_prize(&mut self, prize_id: &PrizeId, funder: &AccountId) {
+        if let Some(mut prize) = self.prizes.get(prize_id) {
+            require!(prize.status == PrizeStatus::ESCROWED, ContractError::InvalidPoolStatus, "prize {} is {:?}, only escrowed prize can be refunded", prize_id, prize.status);
+            prize.status = PrizeStatus::REFUNDED;
+            prize.receiver = Some(funder.clone());
+            self.prizes.insert(prize_id, &prize);
+        }
+    }
+
+    /// An awarded prize its winner never claimed goes back to the creator.
+    pub(crate) fn internal_reclaim_prize(&mut self, prize_id: &PrizeId, creator_id: &AccountId) {
+        if let Some(mut prize) = self.prizes.get(prize_id) {
+            require!(prize.status == PrizeStatus::AWARDED, ContractError::InvalidPoolStatus, "prize {} is {:?}, only awarded prize can be reclaimed", prize_id, prize.status);
+            prize.status = PrizeStatus::REFUNDED;
+            prize.receiver = Some(creator_id.clone());
+            self.prizes.insert(prize_id, &prize);
+        }
+    }
+
+    pub fn view_prize(&self, prize_id: PrizeId) -> PrizeInfo {
+        return self.prizes.get(&prize_id)
+            .unwrap_or_else(|| fail(ContractError::PrizeNotFound, format!("prize {} not exist", prize_id)));
+    }
 }
 
 // #[derive(BorshDeserialize, BorshSerialize,Serialize,Deserialize,Clone,Debug)]
@@ -2,6 +2,7 @@ use std::borrow::Borrow;
 use std::collections::{BinaryHeap, HashMap, HashSet};
 use crate::prize::{NftPrize, Prize};
 use crate::prize::FtPrize;
+use crate::errors::{ContractError, fail};
 use crate::*;
 use crate::{NonFungibleTokenId, FungibleTokenId, Contract, StorageKey};
 use near_sdk::{assert_one_yocto, near_bindgen, AccountId, env, Timestamp};
@@ -12,21 +13,37 @@ use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::env::{block_timestamp, log};
 use near_sdk::serde::{Deserialize, Serialize};
 use crate::StorageKey::PrizePools;
-use crate::utils::{get_block_milli_time, vec_random};
+use crate::utils::{get_block_milli_time, has_gas_for_register_and_transfer, has_gas_for_staking_draw, has_gas_for_transfer, vec_random, weighted_vec_random};
 use std::cmp::Ordering;
 use crate::asset::{Asset, Assets, Ft, Nft};
+use crate::raffle::RafflePool;
+use crate::staking_lottery::StakingLottery;
+use crate::mystery_box::MysteryBox;
 
 pub type PoolId = u64;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub enum VPool {
-    TwitterPool(TwitterPool)
+    TwitterPool(TwitterPool),
+    RafflePool(RafflePool),
+    StakingLottery(StakingLottery),
+    MysteryBox(MysteryBox),
 }
 
 impl VPool {
     pub fn into_twitter_pool(self) -> TwitterPool {
-        match self { VPool::TwitterPool(pool) => pool }
+        match self {
+            VPool::TwitterPool(pool) => pool,
+            _ => fail(ContractError::WrongPoolType, "pool is not a twitter pool")
+        }
+    }
+
+    pub fn twitter_pool(self) -> Option<TwitterPool> {
+        match self {
+            VPool::TwitterPool(pool) => Some(pool),
+            _ => None
+        }
     }
 }
 
@@ -40,6 +57,8 @@ pub enum PoolStatus {
     // after published
     FINISHED,
     DELETED,
+    // published but waiting for start_time
+    SCHEDULED,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone,Debug )]
@@ -73,6 +92,8 @@ pub struct Record {
     pub ft_prize: Option<FtPrize>,
     pub nft_prize: Option<NftPrize>,
     pub receiver: AccountId,
+    // who funded the prize, None when it is the creator
+    pub sponsor: Option<AccountId>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
@@ -83,6 +104,8 @@ pub struct PrizePool {
     pub ft_prizes: Vec<FtPrize>,
     pub nft_prizes: Vec<NftPrize>,
     pub join_accounts: HashSet<AccountId>,
+    // tickets of joiners with more than one entry in the draw
+    pub join_weights: HashMap<AccountId, u32>,
 }
 
 pub trait CountDownDrawPrize {
@@ -94,6 +117,14 @@ pub trait DrawPrize {
 }
 
 impl PrizePool {
+    pub fn weight_of(&self, account_id: &AccountId) -> u64 {
+        return self.join_weights.get(account_id).map(|weight| *weight as u64).unwrap_or(1);
+    }
+
+    pub fn weighted_joiners(&self) -> Vec<(&AccountId, u64)> {
+        return self.join_accounts.iter().map(|account_id| (account_id, self.weight_of(account_id))).collect_vec();
+    }
+
     pub fn new(
         id: PoolId,
         creator_id: &String,
@@ -104,19 +135,20 @@ impl PrizePool {
             ft_prizes: vec![],
             nft_prizes: vec![],
             join_accounts: HashSet::new(),
+            join_weights: HashMap::new(),
         }
     }
 }
 
 pub fn random_distribution_prizes(ft_prizes: &Vec<FtPrize>,
                                   nft_prizes: &Vec<NftPrize>,
-                                  mut joiners: Vec<&AccountId>,
+                                  mut joiners: Vec<(&AccountId, u64)>,
                                   creator: &AccountId) -> HashMap<AccountId, Vec<Prize>> {
     let mut indexs = (0..ft_prizes.len() + nft_prizes.len()).collect_vec();
     let len = indexs.len().clone();
     let mut result: HashMap<AccountId, Vec<Prize>> = HashMap::default();
     for _ in 0..len {
-        let receiver = vec_random(&mut joiners).unwrap_or(creator);
+        let receiver = weighted_vec_random(&mut joiners).unwrap_or(creator);
         let prize_index = vec_random(&mut indexs).unwrap();
         let prize = if prize_index < ft_prizes.len() {
             Prize::FT_PRIZE(ft_prizes[prize_index].clone())
@@ -128,6 +160,12 @@ pub fn random_distribution_prizes(ft_prizes: &Vec<FtPrize>,
     return result;
 }
 
+/// Prizes the creator won as a joiner, they come first in the creator's share of `random_distribution_prizes`,
+/// the rest fell back to the creator because nobody was left to win them.
+pub fn creator_won_count(joiners: &Vec<(&AccountId, u64)>, creator: &AccountId) -> usize {
+    return joiners.iter().filter(|e| e.0 == creator && e.1 > 0).count();
+}
+
 pub struct CreatePrizePoolParam {
     name: String,
     describe: String,
@@ -146,26 +184,74 @@ impl Contract {
         let mut pool = self.internal_get_twitter_pool(&pool_id);
         // 1. check time
         let time_now = get_block_milli_time();
-        assert!(pool.end_time <= time_now, "pool end_time ({}) is before block_timestamp({})", pool.end_time, time_now);
-        // 2. internal transfer prize to user
+        // every stage is queued, the final draw runs once all of them are drawn
+        if !pool.stages.is_empty() {
+            if pool.status == PoolStatus::FINISHED { return; }
+            // an earlier entry may have drawn the stages that came due together
+            if self.internal_draw_stages(&mut pool) && pool.end_time > time_now {
+                self.internal_save_twitter_pool(pool);
+                return;
+            }
+            if pool.end_time > time_now { return; }
+        }
+        require!(pool.end_time <= time_now, ContractError::DrawTimeNotReached, "pool end_time ({}) is after block_timestamp ({})", pool.end_time, time_now);
+        // scratch cards are decided on join, nothing is left to draw
+        if pool.scratch_card.is_some() {
+            self.internal_finish_scratch_card(&mut pool);
+            self.internal_save_twitter_pool(pool);
+            return;
+        }
+        // 2. internal transfer prize to user, pools with a claim deadline keep them until claimed
         let user_prize_map = pool.draw_prize();
+        let claimable = pool.claim_deadline.is_some();
+        let creator_id = pool.prize_pool.creator_id.clone();
+        let creator_wins = creator_won_count(&pool.eligible_joiners(), &creator_id);
+        let mut fees = Assets::default();
         user_prize_map.iter()
             .for_each(|(account_id, prizes)| {
                 let mut account = self.internal_get_account(&account_id);
-                prizes.iter().for_each(|prize| {
+                prizes.iter().enumerate().for_each(|(index, prize)| {
+                    // no fee on the budget that goes back to the creator unwon
+                    let won = account_id != &creator_id || index < creator_wins;
                     let mut record = Record{
                         time: get_block_milli_time(),
                         ft_prize: None,
                         nft_prize: None,
-                        receiver: account_id.clone()
+                        receiver: account_id.clone(),
+                        sponsor: None,
                     };
+                    // the transfer callbacks put the prize back into assets when the transfer fails
+                    let auto_transfer = pool.auto_transfer && has_gas_for_transfer();
+                    if pool.auto_transfer && !auto_transfer {
+                        log!("not enough gas to transfer prize of pool {} to {}, deposit to account", pool_id, account_id);
+                    }
                     match prize {
                         Prize::NFT_PRIZE(nft_prize) => {
-                            account.assets.deposit_nft(&nft_prize.nft);
+                            self.internal_award_prize(&nft_prize.prize_id, account_id);
+                            if auto_transfer {
+                                self.external_send_nft(&account_id, &nft_prize.nft.contract_id, &nft_prize.nft.nft_id);
+                            } else if claimable {
+                                pool.claimable_prizes.entry(account_id.clone()).or_default().deposit_nft(&nft_prize.nft);
+                            } else {
+                                account.assets.deposit_nft(&nft_prize.nft);
+                            }
                             record.nft_prize = Some(nft_prize.clone());
+                            record.sponsor = pool.prize_sponsors.get(&nft_prize.prize_id).cloned();
                         }
                         Prize::FT_PRIZE(ft_prize) => {
-                            account.assets.deposit_ft(&ft_prize.ft);
+                            self.internal_award_prize(&ft_prize.prize_id, account_id);
+                            let ft = if won { self.internal_split_ft_fee(&ft_prize.ft, &mut fees) } else { ft_prize.ft.clone() };
+                            let ft_prize = &FtPrize { prize_id: ft_prize.prize_id, ft };
+                            if auto_transfer && pool.auto_register && has_gas_for_register_and_transfer() {
+                                self.external_register_and_send_ft(&account_id, &ft_prize.ft.contract_id, &ft_prize.ft.balance, &pool_id);
+                            } else if auto_transfer {
+                                self.external_send_ft(&account_id, &ft_prize.ft.contract_id, &ft_prize.ft.balance);
+                            } else if claimable {
+                                pool.claimable_prizes.entry(account_id.clone()).or_default().deposit_ft(&ft_prize.ft);
+                            } else {
+                                account.assets.deposit_ft(&ft_prize.ft);
+                            }
+                            record.sponsor = pool.prize_sponsors.get(&ft_prize.prize_id).cloned();
                             record.ft_prize = Some(ft_prize.clone());
                         }
                     }
@@ -174,7 +260,10 @@ impl Contract {
                 self.internal_save_account(&account_id, account);
             });
 
+        self.internal_deposit_fees(&fees);
+
         pool.status = PoolStatus::FINISHED;
+        self.internal_open_next_round(&mut pool);
         self.internal_save_twitter_pool(pool);
         // self.twitter_prize_pools.insert(&pool_id,&pool);
     }
@@ -206,15 +295,19 @@ impl Contract {
     pub fn pools_prize_draw(&mut self) {
         log!("block time is {}",get_block_milli_time());
         let queue = self.pool_queue.clone();
-        let mut new_queue: Vec<PrizeDrawTime> = vec![];
+        // draws may queue the next round of a pool, so the queue is rebuilt before drawing
+        self.pool_queue = queue.iter().filter(|e| e.1 > get_block_milli_time()).cloned().collect_vec();
         for e in queue {
             if e.1<=get_block_milli_time() {
-                self.prize_draw(e.0.clone());
-            } else {
-                new_queue.push(e.clone());
+                match self.twitter_prize_pools.get(&e.0) {
+                    Some(VPool::RafflePool(_)) => self.raffle_draw(e.0.clone()),
+                    // the draw is asynchronous, wait for the next call when the gas runs short
+                    Some(VPool::StakingLottery(_)) if !has_gas_for_staking_draw() => self.pool_queue.push(e.clone()),
+                    Some(VPool::StakingLottery(_)) => self.staking_lottery_draw(e.0.clone()),
+                    _ => self.prize_draw(e.0.clone()),
+                }
             }
         }
-        self.pool_queue = new_queue;
 
         // log!("block time is {}",get_block_milli_time());
         // while !self.pool_queue.is_empty() && self.pool_queue.peek().unwrap().0 <= get_block_milli_time() {
@@ -238,10 +331,12 @@ mod test_twitter {
     use crate::asset::Ft;
     use crate::prize::{FtPrize, FtPrizeCreateParam, Prize};
     use crate::TwitterPool;
-    use crate::tests::setup_contract;
+    use crate::tests::{PoolBuilder, setup_contract};
     use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, MockedBlockchain};
     use near_sdk_sim::lazy_static_include::syn::export::str;
     use crate::twitter_giveaway::TwitterPoolCreateParam;
+    use crate::prize_pool::PoolStatus;
 
     #[test]
     fn test_queue() {
@@ -259,7 +354,7 @@ mod test_twitter {
     #[test]
     fn test_create_param() {
         let param = TwitterPoolCreateParam {
-            name: None,
+            name: Some("pool".to_string()),
             describe: None,
             cover: None,
             end_time: None,
@@ -269,11 +364,13 @@ mod test_twitter {
                 ft: Ft {
                     contract_id: "wrap.testnet".to_string(),
                     balance: U128::from(1000000000000000000000000),
-                }
+                },
+                winners: None
             }]),
             nft_prizes: None,
             join_accounts: None,
             twitter_link: None,
+            ..Default::default()
         };
         let (mut context, mut contract) = setup_contract();
         contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128::from(1000000000000000000000000));
@@ -286,6 +383,7 @@ mod test_twitter {
     #[test]
     fn test_create() {
         const CREATE_PARAM_RAW: &str = r#"{
+"name": "pool",
 "ft_prizes": [
       {
         "ft": {
@@ -306,4 +404,65 @@ mod test_twitter {
         let pool = contract.create_twitter_pool(param);
         println!("{:?}", pool)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_auto_transfer_draw() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1)])
+            .with(|param| param.auto_transfer = Some(true))
+            .drawn()
+            .build(&mut context, &mut contract);
+
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.status, PoolStatus::FINISHED);
+        assert_eq!(pool.records[0].receiver, accounts(1).to_string());
+        // prize left the contract, nothing is credited internally
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet"), None);
+
+        testing_env!(context.block_timestamp(1638790720000).build());
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1)])
+            .with(|param| param.auto_transfer = Some(false))
+            .drawn()
+            .build(&mut context, &mut contract);
+        assert_eq!(contract.view_twitter_prize_pool(pool_id).status, PoolStatus::FINISHED);
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 100);
+    }
+
+    #[test]
+    fn test_shared_ft_prize() {
+        let (mut context, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(100));
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(1700000),
+            white_list: Some(vec![accounts(1).into(), accounts(2).into()]),
+            ft_prizes: Some(vec![FtPrizeCreateParam {
+                ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(100) },
+                winners: Some(3),
+            }]),
+            ..Default::default()
+        });
+        let shares = contract.view_twitter_prize_pool(pool_id).prize_pool.ft_prizes.iter().map(|e| e.ft.balance.0).collect::<Vec<_>>();
+        assert_eq!(shares, vec![34, 33, 33]);
+        contract.publish_pool(pool_id);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        testing_env!(context.block_timestamp(1700000 * 1000000).build());
+        contract.pools_prize_draw();
+
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.records.len(), 3);
+        // two winners share it, the share left goes back to the creator
+        assert_eq!(pool.records.iter().filter(|e| e.receiver == accounts(0).to_string()).count(), 1);
+        let balance_of = |account_id: usize| contract.view_account_balance(accounts(account_id)).get("wrap.testnet").unwrap().0;
+        assert_eq!(balance_of(0) + balance_of(1) + balance_of(2), 100);
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use crate::*;
+use crate::errors::{ContractError, fail};
+use crate::asset::{ContractId, Ft};
+use crate::prize::{FtPrize, FtPrizeCreateParam, NftPrizeCreateParam, Prize};
+use crate::prize_pool::{creator_won_count, PoolStatus, random_distribution_prizes};
+use crate::utils::{FEE_DIVISOR, get_block_milli_time, log_event};
+use crate::validation::{assert_valid_param, validate_info, validate_prizes};
+use near_sdk::assert_one_yocto;
+use near_sdk::serde_json::json;
+
+/// Ticket token of raffles sold for native NEAR instead of a fungible token.
+pub const NEAR_TOKEN_ID: &str = "near";
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RafflePrize {
+    // prizes escrowed by the creator, the creator keeps all ticket proceeds
+    Fixed,
+    // `winner_share_bps` of the proceeds is split into `winner_count` equal prizes, the creator keeps the rest
+    Proceeds { winner_share_bps: u32, winner_count: u32 },
+}
+
+/// What an `ft_transfer_call` to this contract is for, plain deposits carry an empty msg.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TokenReceiverMessage {
+    BuyRaffleTickets { pool_id: PoolId, count: u32 },
+    OpenMysteryBox { pool_id: PoolId },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RafflePool {
+    pub name: String,
+    pub describe: String,
+    pub cover: String,
+    // join_weights holds the ticket count of every entrant
+    pub prize_pool: PrizePool,
+    pub status: PoolStatus,
+    pub end_time: MilliTimeStamp,
+    pub create_time: MilliTimeStamp,
+    pub ticket_token: ContractId,
+    pub ticket_price: U128,
+    pub max_tickets: u32,
+    pub max_tickets_per_account: u32,
+    pub tickets_sold: u32,
+    pub proceeds: U128,
+    pub prize: RafflePrize,
+    pub records: Vec<Record>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RafflePoolCreateParam {
+    pub name: Option<String>,
+    pub describe: Option<String>,
+    pub cover: Option<String>,
+    pub end_time: MilliTimeStamp,
+    pub ticket_token: ContractId,
+    pub ticket_price: U128,
+    pub max_tickets: u32,
+    pub max_tickets_per_account: u32,
+    pub prize: RafflePrize,
+    pub ft_prizes: Option<Vec<FtPrizeCreateParam>>,
+    pub nft_prizes: Option<Vec<NftPrizeCreateParam>>,
+}
+
+impl From<RafflePool> for VPool {
+    fn from(pool: RafflePool) -> Self {
+        Self::RafflePool(pool)
+    }
+}
+
+impl RafflePool {
+    pub fn tickets_of(&self, account_id: &AccountId) -> u32 {
+        return self.prize_pool.join_weights.get(account_id).cloned().unwrap_or(0);
+    }
+}
+
+impl DrawPrize for RafflePool {
+    fn draw_prize(&self) -> HashMap<AccountId, Vec<Prize>> {
+        return random_distribution_prizes(&self.prize_pool.ft_prizes,
+                                          &self.prize_pool.nft_prizes,
+                                          self.prize_pool.weighted_joiners(),
+                                          &self.prize_pool.creator_id);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    pub(crate) fn internal_get_raffle_pool(&self, id: &PoolId) -> RafflePool {
+        match self.twitter_prize_pools.get(id).unwrap_or_else(|| fail(ContractError::PoolNotFound, format!("pool {} not exist", id))) {
+            VPool::RafflePool(pool) => pool,
+            _ => fail(ContractError::WrongPoolType, format!("pool {} is not a raffle pool", id))
+        }
+    }
+
+    pub(crate) fn internal_save_raffle_pool(&mut self, pool: RafflePool) {
+        self.twitter_prize_pools.insert(&pool.prize_pool.id.clone(), &pool.into());
+    }
+
+    /// Raffles start selling tickets right away and are drawn at `end_time`.
+    #[payable]
+    pub fn create_raffle_pool(&mut self, param: RafflePoolCreateParam) -> PoolId {
+        assert_one_yocto();
+        require!(param.end_time > get_block_milli_time(), ContractError::InvalidTimeRange, "end_time must be in the future");
+        require!(param.ticket_price.0 > 0, ContractError::ZeroAmount, "ticket_price must be positive");
+        require!(param.max_tickets > 0 && param.max_tickets_per_account > 0, ContractError::InvalidRaffleParam, "ticket caps must be positive");
+        assert_valid_param(validate_info(&param.name, &param.describe, &param.cover, false));
+        let ft_prizes = param.ft_prizes.clone().unwrap_or_default();
+        let nft_prizes = param.nft_prizes.clone().unwrap_or_default();
+        assert_valid_param(validate_prizes(&ft_prizes, &nft_prizes));
+        match param.prize {
+            RafflePrize::Fixed => require!(!ft_prizes.is_empty() || !nft_prizes.is_empty(), ContractError::InvalidRaffleParam, "fixed raffle needs prizes"),
+            RafflePrize::Proceeds { winner_share_bps, winner_count } => {
+                require!(ft_prizes.is_empty() && nft_prizes.is_empty(), ContractError::InvalidRaffleParam, "raffle with proceeds prize takes no prizes");
+                require!(winner_share_bps <= FEE_DIVISOR, ContractError::InvalidRaffleParam, "winner_share_bps can't exceed {}", FEE_DIVISOR);
+                require!(winner_count > 0, ContractError::InvalidWinners, "winner_count must be positive");
+            }
+        }
+
+        let creator_id = env::predecessor_account_id();
+        let pool_id = self.next_pool_id();
+        self.internal_use_account(&creator_id, |account| {
+            ft_prizes.iter().for_each(|x| account.assets.withdraw_contract_amount(&x.ft.contract_id, &x.ft.balance.0));
+            nft_prizes.iter().for_each(|x| account.assets.withdraw_nft(&x.nft));
+        });
+        self.internal_charge_nft_fee(&creator_id, nft_prizes.len());
+
+        let mut prize_pool = PrizePool::new(pool_id, &creator_id);
+        prize_pool.ft_prizes = self.internal_new_ft_prizes(pool_id, &ft_prizes);
+        prize_pool.nft_prizes = nft_prizes.iter().map(|e| self.internal_new_nft_prize(pool_id, e.nft.clone())).collect_vec();
+        let pool = RafflePool {
+            name: param.name.unwrap_or_default(),
+            describe: param.describe.unwrap_or_default(),
+            cover: param.cover.unwrap_or_default(),
+            prize_pool,
+            status: PoolStatus::ONGOING,
+            end_time: param.end_time,
+            create_time: get_block_milli_time(),
+            ticket_token: param.ticket_token,
+            ticket_price: param.ticket_price,
+            max_tickets: param.max_tickets,
+            max_tickets_per_account: param.max_tickets_per_account,
+            tickets_sold: 0,
+            proceeds: U128(0),
+            prize: param.prize,
+            records: vec![],
+        };
+        self.pool_queue.push(PrizeDrawTime(pool_id, pool.end_time));
+        self.internal_save_raffle_pool(pool);
+        return pool_id;
+    }
+
+    /// Buy tickets of a raffle sold for NEAR, the attached deposit must pay exactly `count` tickets.
+    #[payable]
+    pub fn buy_raffle_tickets(&mut self, pool_id: PoolId, count: u32) {
+        let cost = self.internal_buy_raffle_tickets(pool_id, &env::predecessor_account_id(), &NEAR_TOKEN_ID.to_string(), count);
+        require!(env::attached_deposit() == cost, ContractError::WrongPayment, "attached deposit must be {}", cost);
+    }
+
+    /// Returns the price of the tickets, the caller makes sure it has been paid.
+    pub(crate) fn internal_buy_raffle_tickets(&mut self, pool_id: PoolId, buyer: &AccountId, token_id: &ContractId, count: u32) -> Balance {
+        let mut pool = self.internal_get_raffle_pool(&pool_id);
+        require!(pool.status == PoolStatus::ONGOING, ContractError::InvalidPoolStatus, "pool can only join in ongoing status");
+        require!(get_block_milli_time() < pool.end_time, ContractError::PoolEnded, "ticket sale has ended");
+        require!(&pool.ticket_token == token_id, ContractError::WrongPayment, "tickets are sold in {}", pool.ticket_token);
+        require!(count > 0, ContractError::ZeroAmount, "count must be positive");
+        require!(pool.tickets_sold + count <= pool.max_tickets, ContractError::SoldOut, "only {} tickets left", pool.max_tickets - pool.tickets_sold);
+        let tickets = pool.tickets_of(buyer) + count;
+        require!(tickets <= pool.max_tickets_per_account, ContractError::TicketLimit, "an account can hold at most {} tickets", pool.max_tickets_per_account);
+
+        let cost = pool.ticket_price.0 * count as u128;
+        pool.tickets_sold += count;
+        pool.proceeds = U128(pool.proceeds.0 + cost);
+        pool.prize_pool.join_accounts.insert(buyer.clone());
+        pool.prize_pool.join_weights.insert(buyer.clone(), tickets);
+        self.internal_save_raffle_pool(pool);
+        log_event("raffle_tickets_bought", json!({
+            "pool_id": pool_id,
+            "account_id": buyer,
+            "count": count,
+        }));
+        return cost;
+    }
+
+    pub(crate) fn internal_pay_proceeds(&mut self, receiver_id: &AccountId, token_id: &ContractId, amount: Balance) {
+        if amount == 0 { return; }
+        if token_id == NEAR_TOKEN_ID {
+            near_sdk::Promise::new(receiver_id.clone()).transfer(amount);
+        } else {
+            self.internal_deposit_ft(receiver_id, token_id, &U128(amount));
+        }
+    }
+
+    #[private]
+    pub(crate) fn raffle_draw(&mut self, pool_id: PoolId) {
+        let mut pool = self.internal_get_raffle_pool(&pool_id);
+        let time_now = get_block_milli_time();
+        require!(pool.end_time <= time_now, ContractError::DrawTimeNotReached, "pool end_time ({}) is after block_timestamp ({})", pool.end_time, time_now);
+
+        // turn the winners' share of the proceeds into prizes
+        let mut creator_proceeds = pool.proceeds.0;
+        if let RafflePrize::Proceeds { winner_share_bps, winner_count } = pool.prize {
+            let share = pool.proceeds.0 * winner_share_bps as u128 / FEE_DIVISOR as u128 / winner_count as u128;
+            if share > 0 {
+                for _ in 0..winner_count {
+                    let prize = self.internal_new_ft_prize(pool_id, Ft { contract_id: pool.ticket_token.clone(), balance: U128(share) });
+                    pool.prize_pool.ft_prizes.push(prize);
+                }
+                creator_proceeds -= share * winner_count as u128;
+            }
+        }
+        let creator_id = pool.prize_pool.creator_id.clone();
+        let ticket_token = pool.ticket_token.clone();
+        self.internal_pay_proceeds(&creator_id, &ticket_token, creator_proceeds);
+
+        let mut fees = Assets::default();
+        let creator_wins = creator_won_count(&pool.prize_pool.weighted_joiners(), &creator_id);
+        pool.draw_prize().iter().for_each(|(account_id, prizes)| {
+            prizes.iter().enumerate().for_each(|(index, prize)| {
+                // no fee on the budget that goes back to the creator unwon
+                let won = account_id != &creator_id || index < creator_wins;
+                let mut record = Record {
+                    time: get_block_milli_time(),
+                    ft_prize: None,
+                    nft_prize: None,
+                    receiver: account_id.clone(),
+                    sponsor: None,
+                };
+                match prize {
+                    Prize::NFT_PRIZE(nft_prize) => {
+                        self.internal_award_prize(&nft_prize.prize_id, account_id);
+                        self.internal_deposit_nft(account_id, &nft_prize.nft.contract_id, &nft_prize.nft.nft_id);
+                        record.nft_prize = Some(nft_prize.clone());
+                    }
+                    Prize::FT_PRIZE(ft_prize) if ft_prize.ft.contract_id == NEAR_TOKEN_ID => {
+                        self.internal_award_prize(&ft_prize.prize_id, account_id);
+                        self.internal_pay_proceeds(account_id, &ft_prize.ft.contract_id, ft_prize.ft.balance.0);
+                        record.ft_prize = Some(ft_prize.clone());
+                    }
+                    Prize::FT_PRIZE(ft_prize) => {
+                        self.internal_award_prize(&ft_prize.prize_id, account_id);
+                        let ft = if won { self.internal_split_ft_fee(&ft_prize.ft, &mut fees) } else { ft_prize.ft.clone() };
+                        let ft_prize = FtPrize { prize_id: ft_prize.prize_id, ft };
+                        self.internal_deposit_ft(account_id, &ft_prize.ft.contract_id, &ft_prize.ft.balance);
+                        record.ft_prize = Some(ft_prize);
+                    }
+                }
+                pool.records.push(record);
+            });
+        });
+        self.internal_deposit_fees(&fees);
+
+        pool.status = PoolStatus::FINISHED;
+        self.internal_save_raffle_pool(pool);
+    }
+
+    pub fn view_raffle_pool(&self, pool_id: PoolId) -> RafflePool {
+        return self.internal_get_raffle_pool(&pool_id);
+    }
+
+    pub fn view_raffle_tickets(&self, pool_id: PoolId, account_id: ValidAccountId) -> u32 {
+        return self.internal_get_raffle_pool(&pool_id).tickets_of(account_id.as_ref());
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_raffle {
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::asset::Ft;
+    use crate::prize::FtPrizeCreateParam;
+    use crate::prize_pool::PoolStatus;
+    use crate::raffle::{NEAR_TOKEN_ID, RafflePoolCreateParam, RafflePrize};
+    use crate::tests::setup_contract;
+    use crate::utils::ONE_NEAR;
+
+    fn raffle_param(ticket_token: &str) -> RafflePoolCreateParam {
+        RafflePoolCreateParam {
+            name: None,
+            describe: None,
+            cover: None,
+            end_time: 2000000,
+            ticket_token: ticket_token.to_string(),
+            ticket_price: U128(ONE_NEAR),
+            max_tickets: 3,
+            max_tickets_per_account: 2,
+            prize: RafflePrize::Proceeds { winner_share_bps: 8000, winner_count: 1 },
+            ft_prizes: None,
+            nft_prizes: None,
+        }
+    }
+
+    #[test]
+    fn test_raffle_with_ft_tickets() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = contract.create_raffle_pool(raffle_param("wrap.testnet"));
+
+        testing_env!(context.predecessor_account_id(ValidAccountId::try_from("wrap.testnet").unwrap()).build());
+        let msg = format!("{{\"BuyRaffleTickets\":{{\"pool_id\":{},\"count\":2}}}}", pool_id);
+        let refund = contract.ft_on_transfer(accounts(1), U128(3 * ONE_NEAR), msg);
+        match refund {
+            PromiseOrValue::Value(refund) => assert_eq!(refund.0, ONE_NEAR),
+            _ => panic!("ticket purchase should refund the change"),
+        }
+        assert_eq!(contract.view_raffle_tickets(pool_id, accounts(1)), 2);
+
+        testing_env!(context.block_timestamp(2000000 * 1000000).build());
+        contract.pools_prize_draw();
+        let pool = contract.view_raffle_pool(pool_id);
+        assert_eq!(pool.status, PoolStatus::FINISHED);
+        assert_eq!(pool.records.len(), 1);
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 2 * ONE_NEAR * 8 / 10);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 2 * ONE_NEAR * 2 / 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "E042_TICKET_LIMIT: an account can hold at most 2 tickets")]
+    fn test_raffle_per_account_cap() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = contract.create_raffle_pool(raffle_param(NEAR_TOKEN_ID));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(2 * ONE_NEAR).build());
+        contract.buy_raffle_tickets(pool_id, 2);
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        contract.buy_raffle_tickets(pool_id, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "E035_INVALID_WINNERS")]
+    fn test_fixed_raffle_with_zero_winners() {
+        let (_, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(100));
+        let mut param = raffle_param(NEAR_TOKEN_ID);
+        param.prize = RafflePrize::Fixed;
+        param.ft_prizes = Some(vec![FtPrizeCreateParam { ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(100) }, winners: Some(0) }]);
+        contract.create_raffle_pool(param);
+    }
+
+    #[test]
+    #[should_panic(expected = "E041_INVALID_RAFFLE_PARAM")]
+    fn test_fixed_raffle_without_prizes() {
+        let (_, mut contract) = setup_contract();
+        let mut param = raffle_param(NEAR_TOKEN_ID);
+        param.prize = RafflePrize::Fixed;
+        contract.create_raffle_pool(param);
+    }
+
+    #[test]
+    #[should_panic(expected = "E037_WRONG_PAYMENT")]
+    fn test_buy_tickets_with_wrong_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = contract.create_raffle_pool(raffle_param(NEAR_TOKEN_ID));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(ONE_NEAR).build());
+        contract.buy_raffle_tickets(pool_id, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "E040_INVALID_MESSAGE")]
+    fn test_ft_on_transfer_with_illegal_msg() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(ValidAccountId::try_from("wrap.testnet").unwrap()).build());
+        contract.ft_on_transfer(accounts(1), U128(ONE_NEAR), "{\"BuyTickets\":{}}".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "E016_DRAW_TIME_NOT_REACHED")]
+    fn test_draw_raffle_before_end_time() {
+        let (_, mut contract) = setup_contract();
+        let pool_id = contract.create_raffle_pool(raffle_param(NEAR_TOKEN_ID));
+        contract.raffle_draw(pool_id);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::*;
+use crate::errors::ContractError;
+use crate::prize::FtPrizeCreateParam;
+use crate::prize_pool::PoolStatus;
+use crate::twitter_giveaway::TwitterPool;
+use crate::utils::{get_block_milli_time, log_event};
+use near_sdk::serde_json::json;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RecurrenceParam {
+    pub interval: MilliTimeStamp,
+    pub rounds: u32,
+}
+
+/// Records of one finished round are `pool.records[records_start..records_end]`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoundHistory {
+    pub round: u32,
+    pub end_time: MilliTimeStamp,
+    pub join_count: u32,
+    pub records_start: u32,
+    pub records_end: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoundRecords {
+    pub round: u32,
+    pub end_time: MilliTimeStamp,
+    pub join_count: u32,
+    pub records: Vec<Record>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Recurrence {
+    pub interval: MilliTimeStamp,
+    pub rounds: u32,
+    // current round, starts from 1
+    pub round: u32,
+    // ft prizes of every round after the first
+    pub round_budget: Vec<FtPrizeCreateParam>,
+    // budget of the rounds to come, taken from the creator at creation
+    pub escrow: Assets,
+    pub history: Vec<RoundHistory>,
+}
+
+impl Recurrence {
+    pub fn new(param: &RecurrenceParam, round_budget: Vec<FtPrizeCreateParam>) -> Self {
+        require!(param.interval > 0, ContractError::InvalidRecurrence, "interval of recurrence must be positive");
+        require!(param.rounds > 0, ContractError::InvalidRecurrence, "rounds of recurrence must be positive");
+        let mut escrow = Assets::default();
+        (1..param.rounds).for_each(|_| round_budget.iter().for_each(|prize| escrow.deposit_ft(&prize.ft)));
+        Recurrence {
+            interval: param.interval,
+            rounds: param.rounds,
+            round: 1,
+            round_budget,
+            escrow,
+            history: vec![],
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    /// Put the drawn round into the history and open the next one when there is any left.
+    pub(crate) fn internal_open_next_round(&mut self, pool: &mut TwitterPool) {
+        let records_end = pool.records.len() as u32;
+        let join_count = pool.prize_pool.join_accounts.len() as u32;
+        let end_time = pool.end_time;
+        let recurrence = match pool.recurrence.as_mut() {
+            Some(recurrence) => recurrence,
+            None => return,
+        };
+        recurrence.history.push(RoundHistory {
+            round: recurrence.round,
+            end_time,
+            join_count,
+            records_start: recurrence.history.last().map(|e| e.records_end).unwrap_or(0),
+            records_end,
+        });
+        if recurrence.round >= recurrence.rounds { return; }
+
+        recurrence.round += 1;
+        for prize in recurrence.round_budget.iter() {
+            recurrence.escrow.withdraw_ft(&prize.ft);
+        }
+        let ft_prizes = self.internal_new_ft_prizes(pool.prize_pool.id, &recurrence.round_budget);
+        let round = recurrence.round;
+        // a late draw starts the next round from now, so it still gets a whole interval
+        pool.end_time = std::cmp::max(get_block_milli_time(), end_time) + recurrence.interval;
+        pool.prize_pool.ft_prizes = ft_prizes;
+        pool.prize_pool.nft_prizes = vec![];
+        pool.prize_pool.join_accounts.clear();
+        pool.prize_pool.join_weights.clear();
+        // bonus tickets and the referral cap count per round
+        pool.referrals.clear();
+        pool.status = PoolStatus::ONGOING;
+        self.pool_queue.push(PrizeDrawTime(pool.prize_pool.id, pool.end_time));
+        log_event("pool_round_opened", json!({
+            "pool_id": pool.prize_pool.id,
+            "round": round,
+            "end_time": pool.end_time,
+        }));
+    }
+
+    pub fn view_twitter_pool_rounds(&self, pool_id: PoolId) -> Vec<RoundRecords> {
+        let pool = self.internal_get_twitter_pool(&pool_id);
+        return pool.recurrence.as_ref()
+            .map(|recurrence| recurrence.history.iter().map(|e| RoundRecords {
+                round: e.round,
+                end_time: e.end_time,
+                join_count: e.join_count,
+                records: pool.records[e.records_start as usize..e.records_end as usize].to_vec(),
+            }).collect_vec())
+            .unwrap_or_default();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_recurrence {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::asset::Nft;
+    use crate::prize::NftPrizeCreateParam;
+    use crate::prize_pool::PoolStatus;
+    use crate::recurrence::RecurrenceParam;
+    use crate::tests::{PoolBuilder, setup_contract};
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
+
+    #[test]
+    fn test_recurring_pool() {
+        let (mut context, mut contract) = setup_contract();
+        // the builder deposits the first round, the second one is escrowed up front too
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(100));
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1)])
+            .with(|param| param.recurrence = Some(RecurrenceParam { interval: 1000, rounds: 2 }))
+            .build(&mut context, &mut contract);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 0);
+
+        testing_env!(context.block_timestamp(1700000 * 1000000).build());
+        contract.pools_prize_draw();
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.status, PoolStatus::ONGOING);
+        assert_eq!(pool.end_time, 1701000);
+        assert_eq!(pool.recurrence.as_ref().unwrap().round, 2);
+        assert!(pool.prize_pool.join_accounts.is_empty());
+        assert_eq!(pool.prize_pool.ft_prizes.len(), 1);
+        assert_eq!(contract.view_prize_pool_queue()[0].1, 1701000);
+
+        testing_env!(context.block_timestamp(1701000 * 1000000).build());
+        contract.pools_prize_draw();
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.status, PoolStatus::FINISHED);
+        assert_eq!(contract.view_prize_pool_queue_len(), 0);
+        let rounds = contract.view_twitter_pool_rounds(pool_id);
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[0].records[0].receiver, accounts(1).to_string());
+        assert_eq!(rounds[0].join_count, 1);
+        // nobody joined the second round, the prize goes back to the creator
+        assert_eq!(rounds[1].records[0].receiver, accounts(0).to_string());
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 100);
+    }
+
+    #[test]
+    fn test_late_draw_opens_full_round() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .with(|param| param.recurrence = Some(RecurrenceParam { interval: 1000, rounds: 2 }))
+            .build(&mut context, &mut contract);
+        testing_env!(context.block_timestamp(1705500 * 1000000).build());
+        contract.pools_prize_draw();
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.status, PoolStatus::ONGOING);
+        assert_eq!(pool.end_time, 1706500);
+    }
+
+    #[test]
+    #[should_panic(expected = "E008_INVALID_RECURRENCE")]
+    fn test_recurring_pool_with_nft_prizes() {
+        let (_, mut contract) = setup_contract();
+        contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(1700000),
+            nft_prizes: Some(vec![NftPrizeCreateParam { nft: Nft { contract_id: "nft.testnet".to_string(), nft_id: "1".to_string() } }]),
+            recurrence: Some(RecurrenceParam { interval: 1000, rounds: 2 }),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "E008_INVALID_RECURRENCE")]
+    fn test_recurrence_without_rounds() {
+        let (_, mut contract) = setup_contract();
+        contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(1700000),
+            recurrence: Some(RecurrenceParam { interval: 1000, rounds: 0 }),
+            ..Default::default()
+        });
+    }
+}
@@ -0,0 +1,171 @@
+use crate::*;
+use crate::twitter_giveaway::TwitterPool;
+use crate::utils::log_event;
+use near_sdk::serde_json::json;
+
+/// Draw tickets a referrer gets for every friend that joined through it.
+pub const REFERRAL_BONUS_TICKETS: u64 = 1;
+
+/// Referrals of one account that count in a pool.
+pub const MAX_REFERRALS_PER_ACCOUNT: u32 = 10;
+
+impl TwitterPool {
+    pub fn referrals_of(&self, account_id: &AccountId) -> u32 {
+        return self.referrals.get(account_id).cloned().unwrap_or(0);
+    }
+
+    /// Draw weight with the bonus tickets of the account's referrals.
+    pub fn weight_of(&self, account_id: &AccountId) -> u64 {
+        return self.prize_pool.weight_of(account_id) + self.referrals_of(account_id) as u64 * REFERRAL_BONUS_TICKETS;
+    }
+
+    /// Count the referral of a joiner that has not joined yet, the error tells why it doesn't count.
+    pub fn add_referral(&mut self, joiner: &AccountId, referrer: &AccountId) -> Result<u32, &'static str> {
+        if joiner == referrer {
+            return Err("self referral");
+        }
+        if self.prize_pool.join_accounts.contains(joiner) {
+            return Err("joiner has joined");
+        }
+        if !self.prize_pool.join_accounts.contains(referrer) {
+            return Err("referrer hasn't joined");
+        }
+        let count = self.referrals_of(referrer);
+        if count >= MAX_REFERRALS_PER_ACCOUNT {
+            return Err("referrer reached the referral cap");
+        }
+        self.referrals.insert(referrer.clone(), count + 1);
+        return Ok(count + 1);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    /// A referral that doesn't count leaves the join as it is.
+    pub(crate) fn internal_add_referral(&mut self, pool: &mut TwitterPool, joiner: &AccountId, referrer: &AccountId) {
+        match pool.add_referral(joiner, referrer) {
+            Ok(count) => log_event("referral_added", json!({
+                "pool_id": pool.prize_pool.id,
+                "account_id": joiner,
+                "referrer": referrer,
+                "count": count,
+            })),
+            Err(reason) => log_event("referral_rejected", json!({
+                "pool_id": pool.prize_pool.id,
+                "account_id": joiner,
+                "referrer": referrer,
+                "reason": reason,
+            })),
+        }
+    }
+
+    /// Referrers of the pool with the most referrals first.
+    pub fn view_top_referrers(&self, pool_id: PoolId, limit: u32) -> Vec<(AccountId, u32)> {
+        let pool = self.internal_get_twitter_pool(&pool_id);
+        return pool.referrals.into_iter()
+            .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)))
+            .take(limit as usize)
+            .collect_vec();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_referral {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::recurrence::RecurrenceParam;
+    use crate::referral::{MAX_REFERRALS_PER_ACCOUNT, REFERRAL_BONUS_TICKETS};
+    use crate::tests::{PoolBuilder, setup_contract};
+
+    fn friend(index: u32) -> ValidAccountId {
+        ValidAccountId::try_from(format!("friend{}.testnet", index)).unwrap()
+    }
+
+    #[test]
+    fn test_referral_bonus() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .white_list((1..5).map(accounts).collect())
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, Some(accounts(1)));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.join_twitter_pool(pool_id, None, None, Some(accounts(1)));
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.join_twitter_pool(pool_id, None, None, Some(accounts(1)));
+        // the creator hasn't joined, so it can't refer
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.join_twitter_pool(pool_id, None, None, Some(accounts(0)));
+
+        assert_eq!(contract.view_top_referrers(pool_id, 10), vec![(accounts(1).to_string(), 2)]);
+        assert_eq!(contract.view_twitter_prize_pool_list().pop().unwrap().referral_count, 2);
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.weight_of(accounts(1).as_ref()), 1 + 2 * REFERRAL_BONUS_TICKETS);
+        assert_eq!(pool.weight_of(accounts(2).as_ref()), 1);
+    }
+
+    #[test]
+    fn test_referral_cap() {
+        let (mut context, mut contract) = setup_contract();
+        let friends = (0..MAX_REFERRALS_PER_ACCOUNT + 1).map(friend).collect_vec();
+        let pool_id = PoolBuilder::new()
+            .white_list(friends.iter().cloned().chain(vec![accounts(1)]).collect())
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        for friend in friends.iter() {
+            testing_env!(context.predecessor_account_id(friend.clone()).build());
+            contract.join_twitter_pool(pool_id, None, None, Some(accounts(1)));
+        }
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.prize_pool.join_accounts.len(), friends.len() + 1);
+        assert_eq!(pool.referrals_of(accounts(1).as_ref()), MAX_REFERRALS_PER_ACCOUNT);
+        assert_eq!(pool.weight_of(accounts(1).as_ref()), 1 + MAX_REFERRALS_PER_ACCOUNT as u64 * REFERRAL_BONUS_TICKETS);
+    }
+
+    #[test]
+    fn test_referral_bonus_in_draw() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .ft_prize(100, None)
+            .white_list(vec![accounts(1), accounts(2)])
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.join_twitter_pool(pool_id, None, None, Some(accounts(1)));
+
+        // with 2 tickets against 1, accounts(1) should win about two thirds of the draws instead of half
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        let wins = (0..=255u8).filter(|seed| {
+            testing_env!(context.random_seed(vec![*seed; 32]).build());
+            pool.draw_prize().contains_key(accounts(1).as_ref())
+        }).count();
+        assert!(wins > 150, "referrer won {} of 256 draws", wins);
+    }
+
+    #[test]
+    fn test_referrals_reset_every_round() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .white_list(vec![accounts(1), accounts(2)])
+            .with(|param| param.recurrence = Some(RecurrenceParam { interval: 1000, rounds: 2 }))
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.join_twitter_pool(pool_id, None, None, Some(accounts(1)));
+        assert_eq!(contract.view_twitter_prize_pool(pool_id).referrals_of(accounts(1).as_ref()), 1);
+
+        testing_env!(context.block_timestamp(1700000 * 1000000).build());
+        contract.pools_prize_draw();
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.recurrence.as_ref().unwrap().round, 2);
+        assert!(pool.referrals.is_empty());
+        assert_eq!(pool.weight_of(accounts(1).as_ref()), 1);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::*;
+use crate::accounts::ext_self;
+use crate::asset::ContractId;
+use crate::errors::ContractError;
+use crate::prize_pool::PoolStatus;
+use crate::utils::{GAS_FOR_TOKEN_GATE_CALLBACK, GAS_FOR_TOKEN_GATE_QUERY, get_block_milli_time, log_event};
+use near_sdk::{ext_contract, PromiseResult};
+use near_sdk::serde_json::json;
+
+#[ext_contract(ext_token_gate)]
+pub trait TokenHolding {
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128;
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
+}
+
+/// A condition a joiner has to meet. Twitter actions and account age are verified off-chain
+/// by the white list admin, token holdings are queried from the token contracts at join time.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Requirement {
+    FollowTwitter { twitter_account: String },
+    Retweet { tweet_link: String },
+    Like { tweet_link: String },
+    HoldNft { contract_id: ContractId, min_count: u32 },
+    HoldFt { contract_id: ContractId, min_amount: U128 },
+    NearAccountAge { min_days: u32 },
+}
+
+impl Requirement {
+    pub fn is_on_chain(&self) -> bool {
+        match self {
+            Requirement::HoldNft { .. } | Requirement::HoldFt { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn validate(&self) {
+        match self {
+            Requirement::FollowTwitter { twitter_account } => require!(!twitter_account.is_empty(), ContractError::InvalidRequirement, "twitter_account of requirement is empty"),
+            Requirement::Retweet { tweet_link } | Requirement::Like { tweet_link } => require!(!tweet_link.is_empty(), ContractError::InvalidRequirement, "tweet_link of requirement is empty"),
+            Requirement::HoldNft { min_count, .. } => require!(*min_count > 0, ContractError::InvalidRequirement, "min_count of requirement must be positive"),
+            Requirement::HoldFt { min_amount, .. } => require!(min_amount.0 > 0, ContractError::InvalidRequirement, "min_amount of requirement must be positive"),
+            Requirement::NearAccountAge { .. } => {}
+        }
+    }
+
+    /// Ask the token contract how much of it the account holds, None for off-chain requirements.
+    pub(crate) fn query_holding(&self, account_id: &AccountId) -> Option<Promise> {
+        match self {
+            Requirement::HoldNft { contract_id, .. } => Some(ext_token_gate::nft_supply_for_owner(
+                account_id.clone(),
+                contract_id,
+                0,
+                GAS_FOR_TOKEN_GATE_QUERY,
+            )),
+            Requirement::HoldFt { contract_id, .. } => Some(ext_token_gate::ft_balance_of(
+                account_id.clone(),
+                contract_id,
+                0,
+                GAS_FOR_TOKEN_GATE_QUERY,
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn is_met_by(&self, holding: u128) -> bool {
+        match self {
+            Requirement::HoldNft { min_count, .. } => holding >= *min_count as u128,
+            Requirement::HoldFt { min_amount, .. } => holding >= min_amount.0,
+            _ => true,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    /// Join right away if the pool has no token gate, otherwise query the holdings and
+    /// finish the join in token_gate_callback. The joiner stays in pending_joins meanwhile.
+    pub(crate) fn internal_check_and_join_twitter_pool(&mut self, mut pool: TwitterPool, joiner: AccountId, referrer: Option<AccountId>) -> PromiseOrValue<bool> {
+        let gates = pool.requirements.iter().filter(|e| e.is_on_chain()).cloned().collect_vec();
+        if gates.is_empty() {
+            self.internal_join_twitter_pool(pool, joiner, referrer);
+            return PromiseOrValue::Value(true);
+        }
+        require!(!pool.prize_pool.join_accounts.contains(&joiner), ContractError::AlreadyJoined, "{} has joined the pool", joiner);
+        require!(pool.pending_joins.insert(joiner.clone()), ContractError::JoinPending, "join of {} is waiting for the holding check", joiner);
+        let pool_id = pool.prize_pool.id;
+        self.internal_save_twitter_pool(pool);
+
+        let check = gates.iter()
+            .filter_map(|gate| gate.query_holding(&joiner))
+            .reduce(|all, query| all.and(query))
+            .unwrap();
+        PromiseOrValue::Promise(check.then(ext_self::token_gate_callback(
+            pool_id,
+            joiner,
+            gates,
+            referrer,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_TOKEN_GATE_CALLBACK,
+        )))
+    }
+
+    #[private]
+    pub fn token_gate_callback(&mut self, pool_id: PoolId, joiner: AccountId, gates: Vec<Requirement>, referrer: Option<AccountId>) -> bool {
+        require!(env::promise_results_count() == gates.len() as u64, ContractError::UnexpectedPromiseResults, "token_gate_callback_invalid");
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        pool.pending_joins.remove(&joiner);
+        // the pool may have ended while the holdings were queried
+        pool.refresh_status();
+        let open = pool.status == PoolStatus::ONGOING && get_block_milli_time() <= pool.join_deadline();
+        let passed = open && gates.iter().enumerate().all(|(index, gate)| {
+            match env::promise_result(index as u64) {
+                PromiseResult::Successful(result) => near_sdk::serde_json::from_slice::<U128>(&result)
+                    .map(|holding| gate.is_met_by(holding.0))
+                    .unwrap_or(false),
+                _ => false,
+            }
+        });
+        if passed {
+            self.internal_join_twitter_pool(pool, joiner, referrer);
+        } else {
+            pool.prize_pool.join_weights.remove(&joiner);
+            self.internal_save_twitter_pool(pool);
+            log_event("token_gate_rejected", json!({
+                "pool_id": pool_id,
+                "account_id": joiner,
+            }));
+        }
+        return passed;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_requirement {
+    use near_sdk::{testing_env, MockedBlockchain, PromiseResult};
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use crate::*;
+    use crate::requirement::Requirement;
+    use crate::tests::{PoolBuilder, setup_contract};
+
+    #[test]
+    fn test_requirements_serde() {
+        let requirements: Vec<Requirement> = near_sdk::serde_json::from_str(r#"[
+            {"FollowTwitter": {"twitter_account": "superise"}},
+            {"HoldFt": {"contract_id": "wrap.testnet", "min_amount": "100"}}
+        ]"#).unwrap();
+        assert!(!requirements[0].is_on_chain());
+        assert_eq!(requirements[1], Requirement::HoldFt { contract_id: "wrap.testnet".to_string(), min_amount: U128(100) });
+    }
+
+    fn finish_check(context: &VMContextBuilder, contract: &mut Contract, pool_id: PoolId, balance: &[u8]) -> bool {
+        testing_env!(
+            context.build(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(balance.to_vec())]
+        );
+        contract.token_gate_callback(
+            pool_id,
+            accounts(1).into(),
+            vec![Requirement::HoldFt { contract_id: "wrap.testnet".to_string(), min_amount: U128(100) }],
+            None,
+        )
+    }
+
+    #[test]
+    fn test_token_gated_join() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .joiners(vec![accounts(1)])
+            .with(|param| param.requirements = Some(vec![Requirement::HoldFt { contract_id: "wrap.testnet".to_string(), min_amount: U128(100) }]))
+            .build(&mut context, &mut contract);
+        assert!(contract.view_twitter_prize_pool(pool_id).pending_joins.contains(accounts(1).as_ref()));
+        assert!(finish_check(&context, &mut contract, pool_id, b"\"100\""));
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert!(pool.pending_joins.is_empty());
+        assert!(pool.prize_pool.join_accounts.contains(accounts(1).as_ref()));
+    }
+
+    #[test]
+    fn test_token_gated_join_below_threshold() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .joiners(vec![accounts(1)])
+            .with(|param| param.requirements = Some(vec![Requirement::HoldFt { contract_id: "wrap.testnet".to_string(), min_amount: U128(100) }]))
+            .build(&mut context, &mut contract);
+        assert!(!finish_check(&context, &mut contract, pool_id, b"\"99\""));
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert!(pool.pending_joins.is_empty());
+        assert!(!pool.prize_pool.join_accounts.contains(accounts(1).as_ref()));
+    }
+
+    #[test]
+    fn test_token_gate_resolved_after_end_time() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .joiners(vec![accounts(1)])
+            .with(|param| param.requirements = Some(vec![Requirement::HoldFt { contract_id: "wrap.testnet".to_string(), min_amount: U128(100) }]))
+            .build(&mut context, &mut contract);
+        testing_env!(context.block_timestamp(2000001 * 1000000).build());
+        assert!(!finish_check(&context, &mut contract, pool_id, b"\"100\""));
+        assert!(!contract.view_twitter_prize_pool(pool_id).prize_pool.join_accounts.contains(accounts(1).as_ref()));
+    }
+
+    #[test]
+    #[should_panic(expected = "E039_JOIN_PENDING")]
+    fn test_concurrent_token_gated_join() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .joiners(vec![accounts(1)])
+            .with(|param| param.requirements = Some(vec![Requirement::HoldFt { contract_id: "wrap.testnet".to_string(), min_amount: U128(100) }]))
+            .build(&mut context, &mut contract);
+        contract.join_twitter_pool(pool_id, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E055_INVALID_REQUIREMENT")]
+    fn test_requirement_without_tweet_link() {
+        Requirement::Retweet { tweet_link: "".to_string() }.validate();
+    }
+}
@@ -0,0 +1,230 @@
+use crate::*;
+use crate::errors::ContractError;
+use crate::prize::{FtPrize, Prize};
+use crate::prize_pool::PoolStatus;
+use crate::twitter_giveaway::TwitterPool;
+use crate::utils::{FEE_DIVISOR, get_block_milli_time, log_event, random_number_with_nonce, vec_random_with_seed};
+use near_sdk::serde_json::json;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ScratchCardParam {
+    pub total_entries: u32,
+}
+
+/// Every join scratches a card, `winning_slots_left` of the `entries_left` cards win.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ScratchCard {
+    pub total_entries: u32,
+    // one slot per prize, fixed when the pool is published
+    pub winning_slots: u32,
+    pub entries_left: u32,
+    pub winning_slots_left: u32,
+}
+
+impl ScratchCard {
+    pub fn new(param: &ScratchCardParam) -> Self {
+        require!(param.total_entries > 0, ContractError::InvalidScratchCard, "total_entries of scratch card must be positive");
+        ScratchCard {
+            total_entries: param.total_entries,
+            winning_slots: 0,
+            entries_left: param.total_entries,
+            winning_slots_left: 0,
+        }
+    }
+
+    /// Chance in basis points that the next card wins.
+    pub fn odds_bps(&self) -> u32 {
+        if self.entries_left == 0 { return 0; }
+        return (self.winning_slots_left as u64 * FEE_DIVISOR as u64 / self.entries_left as u64) as u32;
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        return self.entries_left == 0 || self.winning_slots_left == 0;
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    /// Fix the winning slots to the prizes the pool is published with.
+    pub(crate) fn internal_publish_scratch_card(&self, pool: &mut TwitterPool) {
+        let prize_count = (pool.prize_pool.ft_prizes.len() + pool.prize_pool.nft_prizes.len()) as u32;
+        let card = match pool.scratch_card.as_mut() {
+            Some(card) => card,
+            None => return,
+        };
+        require!(prize_count > 0, ContractError::PrizeNotFound, "scratch card pool needs prizes");
+        require!(prize_count <= card.total_entries, ContractError::InvalidWinners, "{} prizes can't fit in {} entries", prize_count, card.total_entries);
+        card.winning_slots = prize_count;
+        card.winning_slots_left = prize_count;
+    }
+
+    /// Decide right away whether the joiner wins, a winning card takes a random prize into the joiner's assets.
+    pub(crate) fn internal_scratch(&mut self, pool: &mut TwitterPool, joiner: &AccountId) {
+        let pool_id = pool.prize_pool.id;
+        let card = pool.scratch_card.as_mut().unwrap();
+        require!(!card.is_exhausted(), ContractError::InvalidPoolStatus, "all cards of pool {} are scratched", pool_id);
+        let used = card.total_entries - card.entries_left;
+        let nonce = [pool_id.to_le_bytes().to_vec(), used.to_le_bytes().to_vec(), joiner.as_bytes().to_vec()].concat();
+        let seed = random_number_with_nonce(&nonce);
+        // drawing without replacement hands out every slot by the last entry
+        let won = seed % (card.entries_left as u64) < card.winning_slots_left as u64;
+        card.entries_left -= 1;
+        if !won {
+            log_event("pool_scratched", json!({ "pool_id": pool_id, "account_id": joiner, "won": false }));
+            return;
+        }
+        card.winning_slots_left -= 1;
+
+        let ft_count = pool.prize_pool.ft_prizes.len();
+        let mut indexes = (0..ft_count + pool.prize_pool.nft_prizes.len()).collect_vec();
+        let index = vec_random_with_seed(&mut indexes, seed / card.total_entries as u64).unwrap();
+        let prize = if index < ft_count {
+            Prize::FT_PRIZE(pool.prize_pool.ft_prizes.remove(index))
+        } else {
+            Prize::NFT_PRIZE(pool.prize_pool.nft_prizes.remove(index - ft_count))
+        };
+        let mut record = Record {
+            time: get_block_milli_time(),
+            ft_prize: None,
+            nft_prize: None,
+            receiver: joiner.clone(),
+            sponsor: None,
+        };
+        let prize_id = match &prize {
+            Prize::NFT_PRIZE(nft_prize) => {
+                self.internal_deposit_nft(joiner, &nft_prize.nft.contract_id, &nft_prize.nft.nft_id);
+                record.nft_prize = Some(nft_prize.clone());
+                nft_prize.prize_id
+            }
+            Prize::FT_PRIZE(ft_prize) => {
+                let mut fees = Assets::default();
+                let ft_prize = FtPrize { prize_id: ft_prize.prize_id, ft: self.internal_split_ft_fee(&ft_prize.ft, &mut fees) };
+                self.internal_deposit_ft(joiner, &ft_prize.ft.contract_id, &ft_prize.ft.balance);
+                self.internal_deposit_fees(&fees);
+                let prize_id = ft_prize.prize_id;
+                record.ft_prize = Some(ft_prize);
+                prize_id
+            }
+        };
+        self.internal_award_prize(&prize_id, joiner);
+        record.sponsor = pool.prize_sponsors.get(&prize_id).cloned();
+        pool.records.push(record);
+        log_event("pool_scratched", json!({ "pool_id": pool_id, "account_id": joiner, "won": true, "prize_id": prize_id }));
+    }
+
+    /// Finish a scratch card pool, prizes nobody won go back to their funders.
+    pub(crate) fn internal_finish_scratch_card(&mut self, pool: &mut TwitterPool) {
+        if pool.status == PoolStatus::FINISHED { return; }
+        self.internal_refund_pool_prizes(pool);
+        pool.prize_pool.ft_prizes.clear();
+        pool.prize_pool.nft_prizes.clear();
+        pool.status = PoolStatus::FINISHED;
+        log_event("scratch_card_finished", json!({ "pool_id": pool.prize_pool.id }));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_scratch_card {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::prize_pool::PoolStatus;
+    use crate::scratch_card::ScratchCardParam;
+    use crate::tests::{PoolBuilder, setup_contract};
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
+
+    #[test]
+    fn test_scratch_until_prizes_run_out() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .ft_prize(200, Some(2))
+            .white_list(vec![accounts(1), accounts(2), accounts(3)])
+            .with(|param| param.scratch_card = Some(ScratchCardParam { total_entries: 2 }))
+            .build(&mut context, &mut contract);
+        let display = contract.view_twitter_prize_pool_list().pop().unwrap();
+        assert_eq!(display.scratch_odds_bps, Some(10000));
+
+        // as many prizes as entries, every card wins
+        for account in [accounts(1), accounts(2)].iter() {
+            testing_env!(context.predecessor_account_id(account.clone()).build());
+            contract.join_twitter_pool(pool_id, None, None, None);
+            assert_eq!(contract.view_account_balance(account.clone()).get("wrap.testnet").unwrap().0, 100);
+        }
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.status, PoolStatus::FINISHED);
+        assert_eq!(pool.records.len(), 2);
+        assert_eq!(pool.scratch_card.unwrap().winning_slots_left, 0);
+    }
+
+    #[test]
+    fn test_unwon_prizes_refunded_at_end_time() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .ft_prize(200, Some(2))
+            .white_list(vec![accounts(1), accounts(2), accounts(3)])
+            .with(|param| param.scratch_card = Some(ScratchCardParam { total_entries: 1000 }))
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        let won = contract.view_twitter_prize_pool(pool_id).records.len() as u128;
+
+        testing_env!(context.block_timestamp(2000000 * 1000000).build());
+        contract.pools_prize_draw();
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.status, PoolStatus::FINISHED);
+        assert_eq!(pool.records.len() as u128, won);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").map(|e| e.0).unwrap_or(0), 200 - won * 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "E035_INVALID_WINNERS")]
+    fn test_more_prizes_than_entries() {
+        let (mut context, mut contract) = setup_contract();
+        PoolBuilder::new()
+            .ft_prize(200, Some(2))
+            .white_list(vec![accounts(1), accounts(2), accounts(3)])
+            .with(|param| param.scratch_card = Some(ScratchCardParam { total_entries: 1 }))
+            .build(&mut context, &mut contract);
+    }
+
+    #[test]
+    #[should_panic(expected = "E004_INVALID_POOL_STATUS: winning slots of scratch card pool are fixed at publish")]
+    fn test_update_prizes_of_scheduled_scratch_card() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .ft_prize(200, Some(2))
+            .white_list(vec![accounts(1), accounts(2)])
+            .with(|param| {
+                param.start_time = Some(1700000);
+                param.scratch_card = Some(ScratchCardParam { total_entries: 2 });
+            })
+            .build(&mut context, &mut contract);
+        contract.update_twitter_pool(TwitterPoolCreateParam { ft_prizes: Some(vec![]), ..Default::default() }, pool_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "E058_INVALID_SCRATCH_CARD: total_entries of scratch card must be positive")]
+    fn test_scratch_card_without_entries() {
+        let (mut context, mut contract) = setup_contract();
+        PoolBuilder::new()
+            .ft_prize(100, None)
+            .with(|param| param.scratch_card = Some(ScratchCardParam { total_entries: 0 }))
+            .build(&mut context, &mut contract);
+    }
+
+    #[test]
+    #[should_panic(expected = "E058_INVALID_SCRATCH_CARD: scratch card pool can't recur or have claim_deadline")]
+    fn test_scratch_card_with_claim_deadline() {
+        let (mut context, mut contract) = setup_contract();
+        PoolBuilder::new()
+            .ft_prize(100, None)
+            .with(|param| {
+                param.claim_deadline = Some(3000000);
+                param.scratch_card = Some(ScratchCardParam { total_entries: 10 });
+            })
+            .build(&mut context, &mut contract);
+    }
+}
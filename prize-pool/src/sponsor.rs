@@ -0,0 +1,158 @@
+use crate::*;
+use crate::prize::{PrizeId, PrizesParam};
+use crate::utils::log_event;
+use near_sdk::assert_one_yocto;
+use near_sdk::serde_json::json;
+
+impl TwitterPool {
+    /// Who funded the prize, the creator unless a sponsor added it.
+    pub fn sponsor_of(&self, prize_id: &PrizeId) -> &AccountId {
+        self.prize_sponsors.get(prize_id).unwrap_or(&self.prize_pool.creator_id)
+    }
+
+    pub fn is_sponsored(&self, prize_id: &PrizeId) -> bool {
+        self.prize_sponsors.contains_key(prize_id)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    /// Add the caller's tokens to the prizes of a pool that is not drawn yet, taken from the caller's assets.
+    #[payable]
+    pub fn sponsor_pool(&mut self, pool_id: PoolId, prizes: PrizesParam) {
+        assert_one_yocto();
+        let sponsor_id = env::predecessor_account_id();
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        self.internal_add_pool_prizes(&mut pool, &sponsor_id, &prizes);
+        if !pool.sponsors.contains(&sponsor_id) {
+            pool.sponsors.push(sponsor_id.clone());
+        }
+        self.internal_save_twitter_pool(pool);
+        log_event("pool_sponsored", json!({
+            "pool_id": pool_id,
+            "sponsor_id": sponsor_id,
+            "ft_prizes": prizes.ft_prizes.iter().map(|e| &e.ft).collect_vec(),
+            "nft_prizes": prizes.nft_prizes.iter().map(|e| &e.nft).collect_vec(),
+        }));
+    }
+
+    /// Give every prize still in escrow back to whoever funded it, awarded ones are left alone.
+    pub(crate) fn internal_refund_pool_prizes(&mut self, pool: &TwitterPool) {
+        for prize in pool.prize_pool.ft_prizes.iter() {
+            if !self.is_prize_escrowed(&prize.prize_id) { continue; }
+            self.internal_deposit_ft(pool.sponsor_of(&prize.prize_id), &prize.ft.contract_id, &prize.ft.balance);
+            self.internal_refund_prize(&prize.prize_id, pool.sponsor_of(&prize.prize_id));
+        }
+        for prize in pool.prize_pool.nft_prizes.iter() {
+            if !self.is_prize_escrowed(&prize.prize_id) { continue; }
+            self.internal_deposit_nft(pool.sponsor_of(&prize.prize_id), &prize.nft.contract_id, &prize.nft.nft_id);
+            self.internal_refund_prize(&prize.prize_id, pool.sponsor_of(&prize.prize_id));
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_sponsor {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::asset::Ft;
+    use crate::prize::FtPrizeCreateParam;
+    use crate::prize::PrizesParam;
+    use crate::tests::{PoolBuilder, setup_contract};
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
+
+    fn ft_prizes(balance: u128) -> Vec<FtPrizeCreateParam> {
+        vec![FtPrizeCreateParam { ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(balance) }, winners: None }]
+    }
+
+    #[test]
+    fn test_sponsor_pool() {
+        let (mut context, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(2).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .white_list(vec![accounts(1)])
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.sponsor_pool(pool_id, PrizesParam { ft_prizes: ft_prizes(300), nft_prizes: vec![] });
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.sponsors, vec![accounts(2).to_string()]);
+        assert_eq!(pool.prize_pool.ft_prizes.len(), 2);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+        testing_env!(context.block_timestamp(1700000 * 1000000).build());
+        contract.pools_prize_draw();
+        let records = contract.view_twitter_prize_pool(pool_id).records;
+        let sponsored = records.iter().find(|e| e.ft_prize.as_ref().unwrap().ft.balance.0 == 300).unwrap();
+        assert_eq!(sponsored.sponsor, Some(accounts(2).to_string()));
+        let funded = records.iter().find(|e| e.ft_prize.as_ref().unwrap().ft.balance.0 == 100).unwrap();
+        assert_eq!(funded.sponsor, None);
+    }
+
+    #[test]
+    fn test_delete_refunds_sponsors() {
+        let (mut context, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(100));
+        contract.internal_deposit_ft(accounts(2).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            ft_prizes: Some(ft_prizes(100)),
+            ..Default::default()
+        });
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.sponsor_pool(pool_id, PrizesParam { ft_prizes: ft_prizes(300), nft_prizes: vec![] });
+
+        // the creator replacing its prizes leaves the sponsored one alone
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.update_twitter_pool(TwitterPoolCreateParam { ft_prizes: Some(ft_prizes(50)), ..Default::default() }, pool_id);
+        assert_eq!(contract.view_twitter_prize_pool(pool_id).prize_pool.ft_prizes.len(), 2);
+
+        contract.delete_twitter_prize_pool(pool_id);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 100);
+        assert_eq!(contract.view_account_balance(accounts(2)).get("wrap.testnet").unwrap().0, 300);
+    }
+
+    #[test]
+    #[should_panic(expected = "E004_INVALID_POOL_STATUS")]
+    fn test_delete_drawn_pool() {
+        let (mut context, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(2).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1)])
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.sponsor_pool(pool_id, PrizesParam { ft_prizes: ft_prizes(300), nft_prizes: vec![] });
+        testing_env!(context.predecessor_account_id(accounts(0)).block_timestamp(1700000 * 1000000).build());
+        contract.pools_prize_draw();
+
+        let balances = [accounts(0), accounts(1), accounts(2)].iter()
+            .map(|e| contract.view_account_balance(e.clone()).get("wrap.testnet").map(|e| e.0))
+            .collect::<Vec<_>>();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| contract.delete_twitter_prize_pool(pool_id)));
+        let after = [accounts(0), accounts(1), accounts(2)].iter()
+            .map(|e| contract.view_account_balance(e.clone()).get("wrap.testnet").map(|e| e.0))
+            .collect::<Vec<_>>();
+        assert_eq!(balances, after);
+        std::panic::resume_unwind(result.unwrap_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "E004_INVALID_POOL_STATUS")]
+    fn test_sponsor_finished_pool() {
+        let (mut context, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(2).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        let pool_id = PoolBuilder::new()
+            .end_time(1700000)
+            .drawn()
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.sponsor_pool(pool_id, PrizesParam { ft_prizes: ft_prizes(300), nft_prizes: vec![] });
+    }
+}
@@ -0,0 +1,157 @@
+use crate::*;
+use crate::errors::ContractError;
+use crate::twitter_giveaway::TwitterPool;
+use crate::utils::{get_block_milli_time, log_event, weighted_vec_random};
+use near_sdk::serde_json::json;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StageParam {
+    pub end_time: MilliTimeStamp,
+    pub winners: u32,
+}
+
+/// A qualifier drawn before the final, its finalists are the only entrants of the next stage.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Stage {
+    pub end_time: MilliTimeStamp,
+    pub winners: u32,
+    // None until the stage is drawn
+    pub finalists: Option<Vec<AccountId>>,
+}
+
+impl From<&StageParam> for Stage {
+    fn from(param: &StageParam) -> Self {
+        Stage { end_time: param.end_time, winners: param.winners, finalists: None }
+    }
+}
+
+/// Stages run in the order given and all end between `start_time` and the final draw at `end_time`.
+pub fn validate_stages(stages: &[Stage], start_time: MilliTimeStamp, end_time: MilliTimeStamp) {
+    let mut last_end_time = start_time;
+    for stage in stages.iter() {
+        require!(stage.winners > 0, ContractError::InvalidWinners, "winners of a stage must be positive");
+        require!(stage.end_time > last_end_time, ContractError::InvalidTimeRange, "stages must end one after another");
+        last_end_time = stage.end_time;
+    }
+    require!(last_end_time < end_time, ContractError::InvalidTimeRange, "stages must end before end_time");
+}
+
+impl TwitterPool {
+    /// Joiners still in the running, the finalists of the last drawn stage once there is one.
+    pub fn eligible_joiners(&self) -> Vec<(&AccountId, u64)> {
+        match self.stages.iter().rev().find_map(|e| e.finalists.as_ref()) {
+            Some(finalists) => finalists.iter().map(|e| (e, self.weight_of(e))).collect_vec(),
+            None => self.prize_pool.join_accounts.iter().map(|e| (e, self.weight_of(e))).collect_vec(),
+        }
+    }
+
+    /// Joining closes when the first stage ends.
+    pub fn join_deadline(&self) -> MilliTimeStamp {
+        return self.stages.first().map(|e| e.end_time).unwrap_or(self.end_time);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    /// Draw every stage that has ended, returns whether any was drawn.
+    pub(crate) fn internal_draw_stages(&mut self, pool: &mut TwitterPool) -> bool {
+        let now = get_block_milli_time();
+        let mut drawn = false;
+        for index in 0..pool.stages.len() {
+            if pool.stages[index].finalists.is_some() || pool.stages[index].end_time > now { continue; }
+            let mut joiners = pool.eligible_joiners().into_iter().map(|(account_id, weight)| (account_id.clone(), weight)).collect_vec();
+            let winners = pool.stages[index].winners;
+            let finalists = (0..winners).filter_map(|_| weighted_vec_random(&mut joiners)).collect_vec();
+            log_event("pool_stage_drawn", json!({
+                "pool_id": pool.prize_pool.id,
+                "stage": index,
+                "finalists": finalists,
+            }));
+            pool.stages[index].finalists = Some(finalists);
+            drawn = true;
+        }
+        return drawn;
+    }
+
+    pub fn view_pool_stages(&self, pool_id: PoolId) -> Vec<Stage> {
+        return self.internal_get_twitter_pool(&pool_id).stages;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_stage {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::prize_pool::PoolStatus;
+    use crate::scratch_card::ScratchCardParam;
+    use crate::stage::StageParam;
+    use crate::tests::{PoolBuilder, setup_contract};
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
+
+    #[test]
+    fn test_two_stage_draw() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .ft_prize(100, None)
+            .joiners(vec![accounts(1), accounts(2), accounts(3), accounts(4)])
+            .with(|param| param.stages = Some(vec![StageParam { end_time: 1700000, winners: 2 }]))
+            .build(&mut context, &mut contract);
+        assert_eq!(contract.view_prize_pool_queue().len(), 2);
+
+        testing_env!(context.block_timestamp(1700000 * 1000000).build());
+        contract.pools_prize_draw();
+        let finalists = contract.view_pool_stages(pool_id)[0].finalists.clone().unwrap();
+        assert_eq!(finalists.len(), 2);
+        assert_eq!(contract.view_twitter_prize_pool(pool_id).status, PoolStatus::ONGOING);
+
+        testing_env!(context.block_timestamp(2000000 * 1000000).build());
+        contract.pools_prize_draw();
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.status, PoolStatus::FINISHED);
+        assert!(finalists.contains(&pool.records[0].receiver));
+    }
+
+    #[test]
+    #[should_panic(expected = "E006_POOL_ENDED")]
+    fn test_join_after_first_stage() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = PoolBuilder::new()
+            .white_list(vec![accounts(1)])
+            .with(|param| param.stages = Some(vec![StageParam { end_time: 1700000, winners: 2 }]))
+            .build(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(1700001 * 1000000).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E059_INVALID_STAGES: pool with stages can't recur or be a scratch card")]
+    fn test_stages_with_scratch_card() {
+        let (_, mut contract) = setup_contract();
+        contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(2000000),
+            stages: Some(vec![StageParam { end_time: 1700000, winners: 2 }]),
+            scratch_card: Some(ScratchCardParam { total_entries: 10 }),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "E059_INVALID_STAGES: stages can only be set at creation")]
+    fn test_update_stages() {
+        let (_, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(2000000),
+            ..Default::default()
+        });
+        contract.update_twitter_pool(TwitterPoolCreateParam {
+            stages: Some(vec![StageParam { end_time: 1700000, winners: 2 }]),
+            ..Default::default()
+        }, pool_id);
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use crate::*;
+use crate::errors::{ContractError, fail};
+use crate::accounts::ext_self;
+use crate::asset::Ft;
+use crate::prize_pool::PoolStatus;
+use crate::raffle::NEAR_TOKEN_ID;
+use crate::utils::{GAS_FOR_GET_STAKED_BALANCE, GAS_FOR_STAKING_ACTION, GAS_FOR_STAKING_CALLBACK,
+                   GAS_FOR_STAKING_DRAW_CALLBACK, get_block_milli_time, log_event, ONE_NEAR, weighted_vec_random};
+use near_sdk::{assert_one_yocto, ext_contract, EpochHeight, PromiseResult};
+use near_sdk::serde_json::json;
+
+/// Epochs a staking pool keeps unstaked NEAR locked.
+pub const NUM_EPOCHS_TO_UNLOCK: EpochHeight = 4;
+
+pub const MIN_STAKING_DEPOSIT: Balance = ONE_NEAR;
+
+// tickets are counted in milliNEAR × milliseconds
+const POINT_UNIT: Balance = ONE_NEAR / 1000;
+// resolution of the ticket share handed to weighted_vec_random
+const TICKET_SCALE: u128 = 1_000_000_000;
+
+#[ext_contract(ext_staking_pool)]
+pub trait StakingPool {
+    fn deposit_and_stake(&mut self);
+    fn unstake(&mut self, amount: U128);
+    fn withdraw(&mut self, amount: U128);
+    fn get_account_staked_balance(&self, account_id: AccountId) -> U128;
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakingDeposit {
+    // principal currently staked
+    pub amount: U128,
+    // time-weighted tickets of the current round
+    pub points: U128,
+    pub update_time: MilliTimeStamp,
+    // withdrawn principal and won rewards on their way out of the staking pool
+    pub unstaked: U128,
+    pub unstaked_available_epoch: EpochHeight,
+}
+
+impl StakingDeposit {
+    fn new(time: MilliTimeStamp) -> Self {
+        StakingDeposit {
+            amount: U128(0),
+            points: U128(0),
+            update_time: time,
+            unstaked: U128(0),
+            unstaked_available_epoch: 0,
+        }
+    }
+
+    // accrue tickets of the current round up to `time`
+    fn settle(&mut self, time: MilliTimeStamp) {
+        if time > self.update_time {
+            self.points = U128(self.points.0 + self.amount.0 / POINT_UNIT * (time - self.update_time) as u128);
+            self.update_time = time;
+        }
+    }
+}
+
+/// A no-loss lottery, the deposits are delegated to `staking_pool_id` and only
+/// the staking rewards of a round are drawn, to one depositor by time-weighted tickets.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakingLottery {
+    pub name: String,
+    pub describe: String,
+    pub cover: String,
+    pub prize_pool: PrizePool,
+    pub status: PoolStatus,
+    pub staking_pool_id: AccountId,
+    pub round: u32,
+    pub round_interval: MilliTimeStamp,
+    // draw time of the current round
+    pub end_time: MilliTimeStamp,
+    pub create_time: MilliTimeStamp,
+    pub deposits: HashMap<AccountId, StakingDeposit>,
+    pub total_deposit: U128,
+    pub records: Vec<Record>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakingLotteryCreateParam {
+    pub name: Option<String>,
+    pub describe: Option<String>,
+    pub cover: Option<String>,
+    pub staking_pool_id: ValidAccountId,
+    pub round_interval: MilliTimeStamp,
+}
+
+impl From<StakingLottery> for VPool {
+    fn from(pool: StakingLottery) -> Self {
+        Self::StakingLottery(pool)
+    }
+}
+
+impl StakingLottery {
+    fn settle_time(&self) -> MilliTimeStamp {
+        return std::cmp::min(get_block_milli_time(), self.end_time);
+    }
+
+    fn deposit_of(&mut self, account_id: &AccountId) -> &mut StakingDeposit {
+        let time = self.settle_time();
+        let deposit = self.deposits.entry(account_id.clone()).or_insert_with(|| StakingDeposit::new(time));
+        deposit.settle(time);
+        return deposit;
+    }
+
+    /// Every depositor with tickets and its share of all tickets of the round.
+    pub fn weighted_depositors(&self) -> Vec<(AccountId, u64)> {
+        let total: u128 = self.deposits.values().map(|deposit| deposit.points.0).sum();
+        if total == 0 { return vec![]; }
+        return self.deposits.iter()
+            .map(|(account_id, deposit)| (account_id.clone(), (deposit.points.0 * TICKET_SCALE / total) as u64))
+            .filter(|(_, weight)| *weight > 0)
+            .collect_vec();
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    pub(crate) fn internal_get_staking_lottery(&self, id: &PoolId) -> StakingLottery {
+        match self.twitter_prize_pools.get(id).unwrap_or_else(|| fail(ContractError::PoolNotFound, format!("pool {} not exist", id))) {
+            VPool::StakingLottery(pool) => pool,
+            _ => fail(ContractError::WrongPoolType, format!("pool {} is not a staking lottery", id))
+        }
+    }
+
+    pub(crate) fn internal_save_staking_lottery(&mut self, pool: StakingLottery) {
+        self.twitter_prize_pools.insert(&pool.prize_pool.id.clone(), &pool.into());
+    }
+
+    /// One lottery per staking pool, so the rewards on the contract's stake belong to it alone.
+    #[payable]
+    pub fn create_staking_lottery(&mut self, param: StakingLotteryCreateParam) -> PoolId {
+        assert_one_yocto();
+        require!(param.round_interval > 0, ContractError::InvalidTimeRange, "round_interval must be positive");
+        let staking_pool_id: AccountId = param.staking_pool_id.into();
+        require!(self.staking_pool_lotteries.get(&staking_pool_id).is_none(), ContractError::StakingPoolInUse, "staking pool {} is used by another lottery", staking_pool_id);
+
+        let pool_id = self.next_pool_id();
+        let now = get_block_milli_time();
+        let pool = StakingLottery {
+            name: param.name.unwrap_or_default(),
+            describe: param.describe.unwrap_or_default(),
+            cover: param.cover.unwrap_or_default(),
+            prize_pool: PrizePool::new(pool_id, &env::predecessor_account_id()),
+            status: PoolStatus::ONGOING,
+            staking_pool_id: staking_pool_id.clone(),
+            round: 1,
+            round_interval: param.round_interval,
+            end_time: now + param.round_interval,
+            create_time: now,
+            deposits: HashMap::new(),
+            total_deposit: U128(0),
+            records: vec![],
+        };
+        self.staking_pool_lotteries.insert(&staking_pool_id, &pool_id);
+        self.pool_queue.push(PrizeDrawTime(pool_id, pool.end_time));
+        self.internal_save_staking_lottery(pool);
+        return pool_id;
+    }
+
+    #[payable]
+    pub fn deposit_to_staking_lottery(&mut self, pool_id: PoolId) -> Promise {
+        let amount = env::attached_deposit();
+        require!(amount >= MIN_STAKING_DEPOSIT, ContractError::DepositTooSmall, "deposit at least {}", MIN_STAKING_DEPOSIT);
+        let account_id = env::predecessor_account_id();
+        let mut pool = self.internal_get_staking_lottery(&pool_id);
+        require!(pool.status == PoolStatus::ONGOING, ContractError::InvalidPoolStatus, "pool can only join in ongoing status");
+        let deposit = pool.deposit_of(&account_id);
+        deposit.amount = U128(deposit.amount.0 + amount);
+        pool.total_deposit = U128(pool.total_deposit.0 + amount);
+        pool.prize_pool.join_accounts.insert(account_id.clone());
+        let staking_pool_id = pool.staking_pool_id.clone();
+        self.internal_save_staking_lottery(pool);
+
+        ext_staking_pool::deposit_and_stake(&staking_pool_id, amount, GAS_FOR_STAKING_ACTION)
+            .then(ext_self::on_staking_lottery_staked(
+                pool_id,
+                account_id,
+                U128(amount),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_STAKING_CALLBACK,
+            ))
+    }
+
+    /// Unstake principal, it can be claimed with `claim_from_staking_lottery` once the staking pool unlocks it.
+    #[payable]
+    pub fn withdraw_from_staking_lottery(&mut self, pool_id: PoolId, amount: U128) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut pool = self.internal_get_staking_lottery(&pool_id);
+        let deposit = pool.deposit_of(&account_id);
+        require!(amount.0 > 0, ContractError::ZeroAmount, "amount must be positive");
+        require!(deposit.amount.0 >= amount.0, ContractError::InsufficientBalance, "not enough deposit to withdraw");
+        // every unstake locks all unstaked NEAR of the lottery again, so dust withdrawals can't keep it locked
+        let left = deposit.amount.0 - amount.0;
+        require!(amount.0 >= MIN_STAKING_DEPOSIT && (left == 0 || left >= MIN_STAKING_DEPOSIT),
+                 ContractError::WithdrawalTooSmall, "withdraw at least {} and leave none or at least {}", MIN_STAKING_DEPOSIT, MIN_STAKING_DEPOSIT);
+        deposit.amount = U128(deposit.amount.0 - amount.0);
+        deposit.unstaked = U128(deposit.unstaked.0 + amount.0);
+        deposit.unstaked_available_epoch = env::epoch_height() + NUM_EPOCHS_TO_UNLOCK;
+        if deposit.amount.0 == 0 {
+            pool.prize_pool.join_accounts.remove(&account_id);
+        }
+        pool.total_deposit = U128(pool.total_deposit.0 - amount.0);
+        let staking_pool_id = pool.staking_pool_id.clone();
+        self.internal_save_staking_lottery(pool);
+
+        self.external_staking_lottery_unstake(&staking_pool_id, pool_id, &account_id, amount, true)
+    }
+
+    /// Send the unlocked principal and rewards back to the caller.
+    #[payable]
+    pub fn claim_from_staking_lottery(&mut self, pool_id: PoolId) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut pool = self.internal_get_staking_lottery(&pool_id);
+        let deposit = pool.deposits.get_mut(&account_id)
+            .unwrap_or_else(|| fail(ContractError::NothingToWithdraw, "no deposit in this pool"));
+        require!(deposit.unstaked.0 > 0, ContractError::NothingToWithdraw, "nothing to claim");
+        require!(env::epoch_height() >= deposit.unstaked_available_epoch, ContractError::UnstakeLocked, "unstaked NEAR is locked until epoch {}", deposit.unstaked_available_epoch);
+        let amount = deposit.unstaked;
+        deposit.unstaked = U128(0);
+        let staking_pool_id = pool.staking_pool_id.clone();
+        self.internal_save_staking_lottery(pool);
+
+        ext_staking_pool::withdraw(amount, &staking_pool_id, 0, GAS_FOR_STAKING_ACTION)
+            .then(ext_self::on_staking_lottery_withdrawn(
+                pool_id,
+                account_id,
+                amount,
+                &env::current_account_id(),
+                0,
+                GAS_FOR_STAKING_CALLBACK,
+            ))
+    }
+
+    fn external_staking_lottery_unstake(&self, staking_pool_id: &AccountId, pool_id: PoolId, account_id: &AccountId, amount: U128, principal: bool) -> Promise {
+        ext_staking_pool::unstake(amount, staking_pool_id, 0, GAS_FOR_STAKING_ACTION)
+            .then(ext_self::on_staking_lottery_unstaked(
+                pool_id,
+                account_id.clone(),
+                amount,
+                principal,
+                &env::current_account_id(),
+                0,
+                GAS_FOR_STAKING_CALLBACK,
+            ))
+    }
+
+    #[private]
+    pub fn on_staking_lottery_staked(&mut self, pool_id: PoolId, account_id: AccountId, amount: U128) -> bool {
+        require!(env::promise_results_count() == 1, ContractError::UnexpectedPromiseResults, "on_staking_lottery_staked_invalid");
+        if let PromiseResult::Successful(_) = env::promise_result(0) { return true; }
+        // the attached NEAR came back, undo the deposit and refund it
+        let mut pool = self.internal_get_staking_lottery(&pool_id);
+        let deposit = pool.deposit_of(&account_id);
+        deposit.amount = U128(deposit.amount.0 - amount.0);
+        if deposit.amount.0 == 0 {
+            pool.prize_pool.join_accounts.remove(&account_id);
+        }
+        pool.total_deposit = U128(pool.total_deposit.0 - amount.0);
+        self.internal_save_staking_lottery(pool);
+        near_sdk::Promise::new(account_id.clone()).transfer(amount.0);
+        log_event("staking_lottery_stake_failed", json!({ "pool_id": pool_id, "account_id": account_id, "amount": amount }));
+        return false;
+    }
+
+    #[private]
+    pub fn on_staking_lottery_unstaked(&mut self, pool_id: PoolId, account_id: AccountId, amount: U128, principal: bool) -> bool {
+        require!(env::promise_results_count() == 1, ContractError::UnexpectedPromiseResults, "on_staking_lottery_unstaked_invalid");
+        if let PromiseResult::Successful(_) = env::promise_result(0) { return true; }
+        // still staked, principal goes back to the deposit, a reward stays in the pool for the next round
+        let mut pool = self.internal_get_staking_lottery(&pool_id);
+        let deposit = pool.deposit_of(&account_id);
+        deposit.unstaked = U128(deposit.unstaked.0 - amount.0);
+        if principal {
+            deposit.amount = U128(deposit.amount.0 + amount.0);
+            pool.total_deposit = U128(pool.total_deposit.0 + amount.0);
+            pool.prize_pool.join_accounts.insert(account_id.clone());
+        }
+        self.internal_save_staking_lottery(pool);
+        log_event("staking_lottery_unstake_failed", json!({ "pool_id": pool_id, "account_id": account_id, "amount": amount }));
+        return false;
+    }
+
+    #[private]
+    pub fn on_staking_lottery_withdrawn(&mut self, pool_id: PoolId, account_id: AccountId, amount: U128) -> bool {
+        require!(env::promise_results_count() == 1, ContractError::UnexpectedPromiseResults, "on_staking_lottery_withdrawn_invalid");
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                near_sdk::Promise::new(account_id).transfer(amount.0);
+                true
+            }
+            _ => {
+                // another unstake may have locked the balance again, claim later
+                let mut pool = self.internal_get_staking_lottery(&pool_id);
+                let deposit = pool.deposit_of(&account_id);
+                deposit.unstaked = U128(deposit.unstaked.0 + amount.0);
+                self.internal_save_staking_lottery(pool);
+                false
+            }
+        }
+    }
+
+    /// Look up the rewards of the round, the draw finishes in on_staking_lottery_balance.
+    pub(crate) fn staking_lottery_draw(&mut self, pool_id: PoolId) {
+        let pool = self.internal_get_staking_lottery(&pool_id);
+        let time_now = get_block_milli_time();
+        require!(pool.end_time <= time_now, ContractError::DrawTimeNotReached, "pool end_time ({}) is after block_timestamp ({})", pool.end_time, time_now);
+        ext_staking_pool::get_account_staked_balance(env::current_account_id(), &pool.staking_pool_id, 0, GAS_FOR_GET_STAKED_BALANCE)
+            .then(ext_self::on_staking_lottery_balance(
+                pool_id,
+                &env::current_account_id(),
+                0,
+                GAS_FOR_STAKING_DRAW_CALLBACK,
+            ));
+    }
+
+    #[private]
+    pub fn on_staking_lottery_balance(&mut self, pool_id: PoolId) {
+        require!(env::promise_results_count() == 1, ContractError::UnexpectedPromiseResults, "on_staking_lottery_balance_invalid");
+        let mut pool = self.internal_get_staking_lottery(&pool_id);
+        let staked = match env::promise_result(0) {
+            PromiseResult::Successful(result) => near_sdk::serde_json::from_slice::<U128>(&result).ok(),
+            _ => None,
+        };
+        let reward = staked.map(|staked| staked.0.saturating_sub(pool.total_deposit.0)).unwrap_or(0);
+
+        let end_time = pool.end_time;
+        pool.deposits.values_mut().for_each(|deposit| deposit.settle(end_time));
+        let mut depositors = pool.weighted_depositors();
+        let winner = weighted_vec_random(&mut depositors);
+        if let (Some(winner), true) = (winner, reward > 0) {
+            let deposit = pool.deposits.get_mut(&winner).unwrap();
+            deposit.unstaked = U128(deposit.unstaked.0 + reward);
+            deposit.unstaked_available_epoch = env::epoch_height() + NUM_EPOCHS_TO_UNLOCK;
+            let prize = self.internal_new_ft_prize(pool_id, Ft { contract_id: NEAR_TOKEN_ID.to_string(), balance: U128(reward) });
+            self.internal_award_prize(&prize.prize_id, &winner);
+            pool.records.push(Record {
+                time: get_block_milli_time(),
+                ft_prize: Some(prize),
+                nft_prize: None,
+                receiver: winner.clone(),
+                sponsor: None,
+            });
+            self.external_staking_lottery_unstake(&pool.staking_pool_id, pool_id, &winner, U128(reward), false);
+            log_event("staking_lottery_drawn", json!({ "pool_id": pool_id, "round": pool.round, "winner": winner, "reward": U128(reward) }));
+        }
+
+        // open the next round, tickets start over
+        pool.deposits.values_mut().for_each(|deposit| {
+            deposit.points = U128(0);
+            deposit.update_time = std::cmp::max(deposit.update_time, end_time);
+        });
+        pool.round += 1;
+        pool.end_time = end_time + pool.round_interval;
+        self.pool_queue.push(PrizeDrawTime(pool_id, pool.end_time));
+        self.internal_save_staking_lottery(pool);
+    }
+
+    pub fn view_staking_lottery(&self, pool_id: PoolId) -> StakingLottery {
+        return self.internal_get_staking_lottery(&pool_id);
+    }
+
+    pub fn view_staking_deposit(&self, pool_id: PoolId, account_id: ValidAccountId) -> Option<StakingDeposit> {
+        return self.internal_get_staking_lottery(&pool_id).deposits.get(account_id.as_ref()).cloned();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_staking_lottery {
+    use near_sdk::{testing_env, MockedBlockchain, PromiseResult};
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use crate::*;
+    use crate::staking_lottery::StakingLotteryCreateParam;
+    use crate::tests::setup_contract;
+    use crate::utils::ONE_NEAR;
+
+    const ROUND: MilliTimeStamp = 1000;
+
+    /// Lottery of accounts(0) on validator.near, accounts(1) deposits 10 NEAR.
+    fn setup_lottery(context: &mut VMContextBuilder, contract: &mut Contract) -> PoolId {
+        let pool_id = contract.create_staking_lottery(StakingLotteryCreateParam {
+            name: None,
+            describe: None,
+            cover: None,
+            staking_pool_id: ValidAccountId::try_from("validator.near").unwrap(),
+            round_interval: ROUND,
+        });
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(10 * ONE_NEAR).build());
+        contract.deposit_to_staking_lottery(pool_id);
+        pool_id
+    }
+
+    fn staked_balance(context: &VMContextBuilder, contract: &mut Contract, pool_id: PoolId, staked: Balance) {
+        testing_env!(
+            context.build(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(staked)).unwrap())]
+        );
+        contract.on_staking_lottery_balance(pool_id);
+    }
+
+    #[test]
+    fn test_time_weighted_draw() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = setup_lottery(&mut context, &mut contract);
+        let start = contract.view_staking_lottery(pool_id).create_time;
+        // accounts(2) joins halfway with the same amount, so it has half the tickets of accounts(1)
+        testing_env!(context.predecessor_account_id(accounts(2)).block_timestamp((start + ROUND / 2) * 1000000).build());
+        contract.deposit_to_staking_lottery(pool_id);
+
+        testing_env!(context.block_timestamp((start + ROUND) * 1000000).build());
+        contract.pools_prize_draw();
+        assert_eq!(contract.view_prize_pool_queue_len(), 0);
+        staked_balance(&context, &mut contract, pool_id, 21 * ONE_NEAR);
+
+        let pool = contract.view_staking_lottery(pool_id);
+        assert_eq!(pool.round, 2);
+        assert_eq!(pool.end_time, start + 2 * ROUND);
+        assert_eq!(contract.view_prize_pool_queue()[0].1, start + 2 * ROUND);
+        assert_eq!(pool.records.len(), 1);
+        let winner = pool.records[0].receiver.clone();
+        let deposit = pool.deposits.get(&winner).unwrap();
+        assert_eq!(deposit.unstaked.0, ONE_NEAR);
+        assert_eq!(deposit.amount.0, 10 * ONE_NEAR);
+        assert!(pool.deposits.values().all(|deposit| deposit.points.0 == 0));
+    }
+
+    #[test]
+    fn test_withdraw_principal() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = setup_lottery(&mut context, &mut contract);
+        testing_env!(context.epoch_height(10).attached_deposit(1).build());
+        contract.withdraw_from_staking_lottery(pool_id, U128(4 * ONE_NEAR));
+        let deposit = contract.view_staking_deposit(pool_id, accounts(1)).unwrap();
+        assert_eq!(deposit.amount.0, 6 * ONE_NEAR);
+        assert_eq!(deposit.unstaked.0, 4 * ONE_NEAR);
+        assert_eq!(deposit.unstaked_available_epoch, 14);
+        assert_eq!(contract.view_staking_lottery(pool_id).total_deposit.0, 6 * ONE_NEAR);
+
+        testing_env!(
+            context.build(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.on_staking_lottery_unstaked(pool_id, accounts(1).into(), U128(4 * ONE_NEAR), true);
+        let deposit = contract.view_staking_deposit(pool_id, accounts(1)).unwrap();
+        assert_eq!(deposit.amount.0, 10 * ONE_NEAR);
+        assert_eq!(deposit.unstaked.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "E045_UNSTAKE_LOCKED: unstaked NEAR is locked until epoch 4")]
+    fn test_claim_before_unlock() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = setup_lottery(&mut context, &mut contract);
+        testing_env!(context.attached_deposit(1).build());
+        contract.withdraw_from_staking_lottery(pool_id, U128(ONE_NEAR));
+        contract.claim_from_staking_lottery(pool_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "E043_STAKING_POOL_IN_USE")]
+    fn test_two_lotteries_on_one_staking_pool() {
+        let (mut context, mut contract) = setup_contract();
+        setup_lottery(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(0)).attached_deposit(1).build());
+        contract.create_staking_lottery(StakingLotteryCreateParam {
+            name: None,
+            describe: None,
+            cover: None,
+            staking_pool_id: ValidAccountId::try_from("validator.near").unwrap(),
+            round_interval: ROUND,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "E044_DEPOSIT_TOO_SMALL")]
+    fn test_deposit_too_small() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = setup_lottery(&mut context, &mut contract);
+        testing_env!(context.attached_deposit(1).build());
+        contract.deposit_to_staking_lottery(pool_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "E057_WITHDRAWAL_TOO_SMALL")]
+    fn test_withdraw_dust() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = setup_lottery(&mut context, &mut contract);
+        testing_env!(context.attached_deposit(1).build());
+        contract.withdraw_from_staking_lottery(pool_id, U128(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "E057_WITHDRAWAL_TOO_SMALL")]
+    fn test_withdraw_leaving_dust() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = setup_lottery(&mut context, &mut contract);
+        testing_env!(context.attached_deposit(1).build());
+        contract.withdraw_from_staking_lottery(pool_id, U128(10 * ONE_NEAR - 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_withdraw_without_yocto() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = setup_lottery(&mut context, &mut contract);
+        testing_env!(context.attached_deposit(0).build());
+        contract.withdraw_from_staking_lottery(pool_id, U128(ONE_NEAR));
+    }
+}
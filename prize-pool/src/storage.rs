@@ -0,0 +1,265 @@
+use crate::*;
+use crate::accounts::ext_self;
+use crate::errors::ContractError;
+use crate::prize_pool::{PoolId, PoolStatus};
+use crate::utils::{GAS_FOR_REGISTER_CALLBACK, GAS_FOR_STORAGE_BALANCE_OF, GAS_FOR_STORAGE_DEPOSIT,
+                   GAS_FOR_STORAGE_DEPOSIT_CALLBACK, log_event, STORAGE_DEPOSIT_FOR_FT_REGISTRATION};
+use near_sdk::{assert_one_yocto, ext_contract, Balance, PromiseResult};
+use near_sdk::serde_json::{json, Value};
+
+#[ext_contract(ext_storage_management)]
+pub trait StorageManagement {
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<Value>;
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> Value;
+}
+
+/// Who paid the NEP-145 registration of a prize receiver.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum StoragePayer {
+    Pool(PoolId),
+    Platform,
+}
+
+#[near_bindgen]
+impl Contract {
+
+    // check the receiver's registration on the token contract before sending ft
+    pub(crate) fn external_register_and_send_ft(
+        &self,
+        received_id: &AccountId,
+        token_id: &AccountId,
+        amount: &U128,
+        pool_id: &PoolId,
+    ) -> Promise {
+        ext_storage_management::storage_balance_of(
+            received_id.clone(),
+            token_id,
+            0,
+            GAS_FOR_STORAGE_BALANCE_OF,
+        )
+            .then(ext_self::storage_balance_of_callback(
+                received_id.clone(),
+                token_id.clone(),
+                amount.clone(),
+                pool_id.clone(),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_REGISTER_CALLBACK,
+            ))
+    }
+
+    #[private]
+    pub fn storage_balance_of_callback(
+        &mut self,
+        received_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        pool_id: PoolId,
+    ) -> PromiseOrValue<()> {
+        require!(env::promise_results_count() == 1, ContractError::UnexpectedPromiseResults, "storage_balance_of_callback_invalid");
+        let registered = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(result) => near_sdk::serde_json::from_slice::<Option<Value>>(&result)
+                .map(|balance| balance.is_some())
+                .unwrap_or(true),
+            PromiseResult::Failed => {
+                // token doesn't support storage management, try to transfer anyway
+                log_event("storage_balance_of_failed", json!({
+                    "pool_id": pool_id,
+                    "account_id": received_id,
+                    "token_id": token_id,
+                }));
+                true
+            }
+        };
+        if registered {
+            return PromiseOrValue::Promise(self.external_send_ft(&received_id, &token_id, &amount));
+        }
+
+        match self.internal_take_storage_budget(&pool_id, STORAGE_DEPOSIT_FOR_FT_REGISTRATION) {
+            Some(payer) => PromiseOrValue::Promise(ext_storage_management::storage_deposit(
+                Some(received_id.clone()),
+                Some(true),
+                &token_id,
+                STORAGE_DEPOSIT_FOR_FT_REGISTRATION,
+                GAS_FOR_STORAGE_DEPOSIT,
+            )
+                .then(ext_self::storage_deposit_callback(
+                    received_id,
+                    token_id,
+                    amount,
+                    payer,
+                    &env::current_account_id(),
+                    0,
+                    GAS_FOR_STORAGE_DEPOSIT_CALLBACK,
+                ))),
+            None => {
+                log_event("storage_budget_insufficient", json!({
+                    "pool_id": pool_id,
+                    "account_id": received_id,
+                    "token_id": token_id,
+                }));
+                self.internal_deposit_ft(&received_id, &token_id, &amount);
+                PromiseOrValue::Value(())
+            }
+        }
+    }
+
+    #[private]
+    pub fn storage_deposit_callback(
+        &mut self,
+        received_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        payer: StoragePayer,
+    ) -> PromiseOrValue<()> {
+        require!(env::promise_results_count() == 1, ContractError::UnexpectedPromiseResults, "storage_deposit_callback_invalid");
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(_) => {
+                log_event("ft_receiver_registered", json!({
+                    "account_id": received_id,
+                    "token_id": token_id,
+                    "payer": payer,
+                }));
+                PromiseOrValue::Promise(self.external_send_ft(&received_id, &token_id, &amount))
+            }
+            PromiseResult::Failed => {
+                // the attached deposit came back to the contract, so does the budget
+                log_event("ft_register_failed", json!({
+                    "account_id": received_id,
+                    "token_id": token_id,
+                    "payer": payer,
+                }));
+                self.internal_refund_storage_budget(&payer, STORAGE_DEPOSIT_FOR_FT_REGISTRATION);
+                self.internal_deposit_ft(&received_id, &token_id, &amount);
+                PromiseOrValue::Value(())
+            }
+        }
+    }
+
+    // charge the pool's own budget first, then the platform budget
+    pub(crate) fn internal_take_storage_budget(&mut self, pool_id: &PoolId, amount: Balance) -> Option<StoragePayer> {
+        if let Some(VPool::TwitterPool(mut pool)) = self.twitter_prize_pools.get(pool_id) {
+            if pool.storage_budget >= amount {
+                pool.storage_budget -= amount;
+                self.internal_save_twitter_pool(pool);
+                return Some(StoragePayer::Pool(pool_id.clone()));
+            }
+        }
+        if self.storage_budget >= amount {
+            self.storage_budget -= amount;
+            return Some(StoragePayer::Platform);
+        }
+        return None;
+    }
+
+    pub(crate) fn internal_refund_storage_budget(&mut self, payer: &StoragePayer, amount: Balance) {
+        match payer {
+            StoragePayer::Pool(pool_id) => match self.twitter_prize_pools.get(pool_id) {
+                Some(VPool::TwitterPool(mut pool)) => {
+                    pool.storage_budget += amount;
+                    self.internal_save_twitter_pool(pool);
+                }
+                _ => self.storage_budget += amount,
+            },
+            StoragePayer::Platform => self.storage_budget += amount,
+        }
+    }
+
+    /// Top up the platform budget used to register prize receivers on token contracts.
+    #[payable]
+    pub fn fund_storage_budget(&mut self) {
+        require!(env::attached_deposit() > 0, ContractError::ZeroAmount, "attach NEAR to fund storage budget");
+        self.storage_budget += env::attached_deposit();
+    }
+
+    #[payable]
+    pub fn withdraw_storage_budget(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        require!(env::predecessor_account_id() == self.admin, ContractError::NoAuthority, "only admin can withdraw storage budget");
+        require!(self.storage_budget >= amount.0, ContractError::InsufficientBalance, "storage budget is {}", self.storage_budget);
+        self.storage_budget -= amount.0;
+        Promise::new(self.admin.clone()).transfer(amount.0)
+    }
+
+    pub fn view_storage_budget(&self) -> U128 {
+        return self.storage_budget.into();
+    }
+
+    /// Creator tops up the budget used to register the winners of its pool.
+    #[payable]
+    pub fn fund_pool_storage_budget(&mut self, pool_id: PoolId) {
+        require!(env::attached_deposit() > 0, ContractError::ZeroAmount, "attach NEAR to fund storage budget");
+        self.internal_creator_use_twitter_pool(&pool_id, |pool| {
+            require!(pool.status != PoolStatus::FINISHED, ContractError::InvalidPoolStatus, "pool is finished");
+            pool.storage_budget += env::attached_deposit();
+        });
+    }
+
+    /// Creator takes back what's left of the pool's storage budget after the draw.
+    #[payable]
+    pub fn withdraw_pool_storage_budget(&mut self, pool_id: PoolId) -> Promise {
+        assert_one_yocto();
+        let mut left: Balance = 0;
+        self.internal_creator_use_twitter_pool(&pool_id, |pool| {
+            require!(pool.status == PoolStatus::FINISHED, ContractError::InvalidPoolStatus, "only finished pool can withdraw storage budget");
+            left = pool.storage_budget;
+            pool.storage_budget = 0;
+        });
+        require!(left > 0, ContractError::NothingToWithdraw, "no storage budget left");
+        Promise::new(env::predecessor_account_id()).transfer(left)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_storage {
+    use near_sdk::{testing_env, MockedBlockchain, PromiseResult};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::tests::setup_contract;
+    use crate::storage::StoragePayer;
+    use crate::utils::STORAGE_DEPOSIT_FOR_FT_REGISTRATION;
+
+    #[test]
+    fn test_unregistered_receiver() {
+        let (context, mut contract) = setup_contract();
+        testing_env!(
+            context.build(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"null".to_vec())]
+        );
+        // no budget at all, the prize stays in the contract
+        contract.storage_balance_of_callback(accounts(1).into(), "wrap.testnet".to_string(), U128(100), 1);
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 100);
+
+        contract.storage_budget = STORAGE_DEPOSIT_FOR_FT_REGISTRATION;
+        contract.storage_balance_of_callback(accounts(1).into(), "wrap.testnet".to_string(), U128(100), 1);
+        assert_eq!(contract.view_storage_budget().0, 0);
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 100);
+
+        testing_env!(
+            context.build(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.storage_deposit_callback(accounts(1).into(), "wrap.testnet".to_string(), U128(100), StoragePayer::Platform);
+        assert_eq!(contract.view_storage_budget().0, STORAGE_DEPOSIT_FOR_FT_REGISTRATION);
+        assert_eq!(contract.view_account_balance(accounts(1)).get("wrap.testnet").unwrap().0, 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "E023_NO_AUTHORITY")]
+    fn test_withdraw_storage_budget_by_other() {
+        let (mut context, mut contract) = setup_contract();
+        contract.storage_budget = STORAGE_DEPOSIT_FOR_FT_REGISTRATION;
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw_storage_budget(U128(STORAGE_DEPOSIT_FOR_FT_REGISTRATION));
+    }
+}
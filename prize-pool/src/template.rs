@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use crate::*;
+use crate::errors::{ContractError, fail};
+use crate::prize::{FtPrizeCreateParam, NftPrizeCreateParam};
+use crate::requirement::Requirement;
+use crate::twitter_giveaway::TwitterPoolCreateParam;
+use crate::utils::get_block_milli_time;
+use near_sdk::json_types::Base58CryptoHash;
+
+pub type TemplateId = String;
+
+/// What a creator re-uses between pools, `duration` becomes `end_time` counted from the creation.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolTemplate {
+    pub name: String,
+    pub describe: String,
+    pub cover: String,
+    pub requirements: Vec<Requirement>,
+    pub ft_prizes: Vec<FtPrizeCreateParam>,
+    pub nft_prizes: Vec<NftPrizeCreateParam>,
+    pub white_list: Vec<AccountId>,
+    pub white_list_root: Option<Base58CryptoHash>,
+    pub twitter_link: String,
+    pub duration: MilliTimeStamp,
+}
+
+impl PoolTemplate {
+    pub fn to_create_param(&self) -> TwitterPoolCreateParam {
+        TwitterPoolCreateParam {
+            name: Some(self.name.clone()),
+            describe: Some(self.describe.clone()),
+            cover: Some(self.cover.clone()),
+            end_time: Some(get_block_milli_time() + self.duration),
+            white_list: Some(self.white_list.clone()),
+            requirements: Some(self.requirements.clone()),
+            ft_prizes: Some(self.ft_prizes.clone()),
+            nft_prizes: Some(self.nft_prizes.clone()),
+            twitter_link: Some(self.twitter_link.clone()),
+            white_list_root: self.white_list_root,
+            ..Default::default()
+        }
+    }
+}
+
+impl TwitterPoolCreateParam {
+    /// Fields set in `self` win over the ones of `base`.
+    pub fn or(self, base: TwitterPoolCreateParam) -> TwitterPoolCreateParam {
+        TwitterPoolCreateParam {
+            name: self.name.or(base.name),
+            describe: self.describe.or(base.describe),
+            cover: self.cover.or(base.cover),
+            start_time: self.start_time.or(base.start_time),
+            end_time: self.end_time.or(base.end_time),
+            white_list: self.white_list.or(base.white_list),
+            requirements: self.requirements.or(base.requirements),
+            ft_prizes: self.ft_prizes.or(base.ft_prizes),
+            nft_prizes: self.nft_prizes.or(base.nft_prizes),
+            join_accounts: self.join_accounts.or(base.join_accounts),
+            twitter_link: self.twitter_link.or(base.twitter_link),
+            auto_transfer: self.auto_transfer.or(base.auto_transfer),
+            auto_register: self.auto_register.or(base.auto_register),
+            claim_deadline: self.claim_deadline.or(base.claim_deadline),
+            rollover_pool: self.rollover_pool.or(base.rollover_pool),
+            white_list_root: self.white_list_root.or(base.white_list_root),
+            recurrence: self.recurrence.or(base.recurrence),
+            scratch_card: self.scratch_card.or(base.scratch_card),
+            stages: self.stages.or(base.stages),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    /// Create or replace a template of the caller.
+    pub fn save_pool_template(&mut self, template_id: TemplateId, template: PoolTemplate) {
+        require!(template.duration > 0, ContractError::InvalidTimeRange, "duration of template must be positive");
+        template.requirements.iter().for_each(Requirement::validate);
+        let creator_id = env::predecessor_account_id();
+        let mut templates = self.pool_templates.get(&creator_id).unwrap_or_default();
+        templates.insert(template_id, template);
+        self.pool_templates.insert(&creator_id, &templates);
+    }
+
+    pub fn remove_pool_template(&mut self, template_id: TemplateId) {
+        let creator_id = env::predecessor_account_id();
+        let mut templates = self.pool_templates.get(&creator_id).unwrap_or_default();
+        templates.remove(&template_id)
+            .unwrap_or_else(|| fail(ContractError::TemplateNotFound, format!("template {} not exist", template_id)));
+        self.pool_templates.insert(&creator_id, &templates);
+    }
+
+    /// Create a pool from one of the caller's templates, prizes are taken from the caller's assets.
+    #[payable]
+    pub fn create_pool_from_template(&mut self, template_id: TemplateId, overrides: Option<TwitterPoolCreateParam>) -> PoolId {
+        let template = self.pool_templates.get(&env::predecessor_account_id())
+            .and_then(|templates| templates.get(&template_id).cloned())
+            .unwrap_or_else(|| fail(ContractError::TemplateNotFound, format!("template {} not exist", template_id)));
+        let param = overrides.unwrap_or_default().or(template.to_create_param());
+        return self.create_twitter_pool(param);
+    }
+
+    pub fn view_pool_templates(&self, account_id: ValidAccountId) -> HashMap<TemplateId, PoolTemplate> {
+        return self.pool_templates.get(account_id.as_ref()).unwrap_or_default();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_template {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::asset::Ft;
+    use crate::prize::FtPrizeCreateParam;
+    use crate::template::PoolTemplate;
+    use crate::tests::setup_contract;
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
+    use crate::utils::get_block_milli_time;
+
+    #[test]
+    fn test_create_pool_from_template() {
+        let (_, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        contract.save_pool_template("weekly".to_string(), PoolTemplate {
+            name: "weekly giveaway".to_string(),
+            describe: "".to_string(),
+            cover: "".to_string(),
+            requirements: vec![],
+            ft_prizes: vec![FtPrizeCreateParam { ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(100) }, winners: None }],
+            nft_prizes: vec![],
+            white_list: vec![accounts(1).into()],
+            white_list_root: None,
+            twitter_link: "".to_string(),
+            duration: 7 * 24 * 60 * 60 * 1000,
+        });
+        assert!(contract.view_pool_templates(accounts(0)).contains_key("weekly"));
+
+        let pool_id = contract.create_pool_from_template("weekly".to_string(), Some(TwitterPoolCreateParam {
+            name: Some("week 2".to_string()),
+            ..Default::default()
+        }));
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.name, "week 2");
+        assert_eq!(pool.end_time, get_block_milli_time() + 7 * 24 * 60 * 60 * 1000);
+        assert!(pool.white_list.contains(accounts(1).as_ref()));
+        assert_eq!(pool.prize_pool.ft_prizes[0].ft.balance.0, 100);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "E054_TEMPLATE_NOT_FOUND")]
+    fn test_template_of_other_creator() {
+        let (mut context, mut contract) = setup_contract();
+        contract.save_pool_template("weekly".to_string(), PoolTemplate {
+            name: "".to_string(),
+            describe: "".to_string(),
+            cover: "".to_string(),
+            requirements: vec![],
+            ft_prizes: vec![],
+            nft_prizes: vec![],
+            white_list: vec![],
+            white_list_root: None,
+            twitter_link: "".to_string(),
+            duration: 1,
+        });
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.create_pool_from_template("weekly".to_string(), None);
+    }
+}
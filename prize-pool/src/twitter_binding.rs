@@ -0,0 +1,166 @@
+use crate::*;
+use crate::errors::{ContractError, fail};
+use crate::twitter_giveaway::TwitterAccount;
+use crate::utils::get_block_milli_time;
+
+/// Default time a twitter account or near account has to wait before it can be bound again, 7 days.
+pub const DEFAULT_TWITTER_BINDING_COOLDOWN: MilliTimeStamp = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TwitterBinding {
+    pub twitter_account: TwitterAccount,
+    pub account_id: AccountId,
+    pub bind_time: MilliTimeStamp,
+}
+
+#[near_bindgen]
+impl Contract {
+
+    // a twitter account and a near account can only be bound to each other
+    pub(crate) fn internal_assert_twitter_binding_free(&self, account_id: &AccountId, twitter_account: &TwitterAccount) {
+        if let Some(binding) = self.twitter_bindings.get(twitter_account) {
+            require!(&binding.account_id == account_id, ContractError::TwitterAccountBound, "this twitter account {} has been used!", twitter_account);
+        }
+        if let Some(bound) = self.near_twitter_bindings.get(account_id) {
+            require!(&bound == twitter_account, ContractError::TwitterAccountBound, "account {} has been bound to twitter account {}", account_id, bound);
+        }
+    }
+
+    fn internal_assert_binding_cooldown(&self, account_id: &AccountId, twitter_account: &TwitterAccount) {
+        let now = get_block_milli_time();
+        let cooldown = self.twitter_binding_cooldown;
+        if let Some(time) = self.twitter_unbind_time.get(twitter_account) {
+            require!(now >= time + cooldown, ContractError::BindingCooldown, "twitter account {} can't be bound again before {}", twitter_account, time + cooldown);
+        }
+        if let Some(time) = self.near_unbind_time.get(account_id) {
+            require!(now >= time + cooldown, ContractError::BindingCooldown, "account {} can't be bound again before {}", account_id, time + cooldown);
+        }
+    }
+
+    /// Bind if neither side is bound yet, does nothing if they are already bound to each other.
+    pub(crate) fn internal_bind_twitter_account(&mut self, account_id: &AccountId, twitter_account: &TwitterAccount) {
+        self.internal_assert_twitter_binding_free(account_id, twitter_account);
+        if self.twitter_bindings.get(twitter_account).is_some() { return; }
+        self.internal_assert_binding_cooldown(account_id, twitter_account);
+        self.twitter_bindings.insert(twitter_account, &TwitterBinding {
+            twitter_account: twitter_account.clone(),
+            account_id: account_id.clone(),
+            bind_time: get_block_milli_time(),
+        });
+        self.near_twitter_bindings.insert(account_id, twitter_account);
+    }
+
+    fn internal_unbind_twitter_account(&mut self, binding: &TwitterBinding) {
+        let now = get_block_milli_time();
+        self.twitter_bindings.remove(&binding.twitter_account);
+        self.near_twitter_bindings.remove(&binding.account_id);
+        self.twitter_unbind_time.insert(&binding.twitter_account, &now);
+        self.near_unbind_time.insert(&binding.account_id, &now);
+    }
+
+    fn assert_binding_admin(&self) {
+        let signer = env::predecessor_account_id();
+        require!(signer == self.white_list_admin || signer == self.admin, ContractError::NoAuthority, "no authority change twitter binding");
+    }
+
+    pub fn bind_twitter_account(&mut self, account_id: ValidAccountId, twitter_account: TwitterAccount) {
+        self.assert_binding_admin();
+        self.internal_bind_twitter_account(account_id.as_ref(), &twitter_account);
+    }
+
+    /// The bound near account or the admin removes a binding, both sides enter the cooldown.
+    pub fn unbind_twitter_account(&mut self, twitter_account: TwitterAccount) {
+        let binding = self.twitter_bindings.get(&twitter_account)
+            .unwrap_or_else(|| fail(ContractError::TwitterAccountNotBound, format!("twitter account {} not bound", twitter_account)));
+        let signer = env::predecessor_account_id();
+        if signer != binding.account_id {
+            self.assert_binding_admin();
+        }
+        self.internal_unbind_twitter_account(&binding);
+    }
+
+    /// Move a twitter account to another near account, allowed once the current binding is older than the cooldown.
+    pub fn rebind_twitter_account(&mut self, twitter_account: TwitterAccount, account_id: ValidAccountId) {
+        self.assert_binding_admin();
+        let binding = self.twitter_bindings.get(&twitter_account)
+            .unwrap_or_else(|| fail(ContractError::TwitterAccountNotBound, format!("twitter account {} not bound", twitter_account)));
+        let available_time = binding.bind_time + self.twitter_binding_cooldown;
+        require!(get_block_milli_time() >= available_time, ContractError::BindingCooldown, "twitter account {} can't be rebound before {}", twitter_account, available_time);
+        require!(self.near_twitter_bindings.get(account_id.as_ref()).is_none(), ContractError::TwitterAccountBound, "account {} has been bound", account_id);
+        if let Some(time) = self.near_unbind_time.get(account_id.as_ref()) {
+            require!(get_block_milli_time() >= time + self.twitter_binding_cooldown, ContractError::BindingCooldown, "account {} can't be bound again before {}", account_id, time + self.twitter_binding_cooldown);
+        }
+        self.internal_unbind_twitter_account(&binding);
+        self.twitter_bindings.insert(&twitter_account, &TwitterBinding {
+            twitter_account: twitter_account.clone(),
+            account_id: account_id.clone().into(),
+            bind_time: get_block_milli_time(),
+        });
+        self.near_twitter_bindings.insert(account_id.as_ref(), &twitter_account);
+    }
+
+    pub fn set_twitter_binding_cooldown(&mut self, cooldown: MilliTimeStamp) {
+        require!(env::predecessor_account_id() == self.admin, ContractError::NoAuthority, "only admin can set binding cooldown");
+        self.twitter_binding_cooldown = cooldown;
+    }
+
+    /// Look the binding up by twitter account or by near account.
+    pub fn view_twitter_binding(&self, twitter_account: Option<TwitterAccount>, account_id: Option<ValidAccountId>) -> Option<TwitterBinding> {
+        let twitter_account = twitter_account
+            .or_else(|| account_id.and_then(|account_id| self.near_twitter_bindings.get(account_id.as_ref())));
+        return twitter_account.and_then(|twitter_account| self.twitter_bindings.get(&twitter_account));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_twitter_binding {
+    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::test_utils::accounts;
+    use crate::tests::setup_contract;
+    use crate::twitter_binding::DEFAULT_TWITTER_BINDING_COOLDOWN;
+
+    #[test]
+    fn test_bind_unbind_rebind() {
+        let (mut context, mut contract) = setup_contract();
+        contract.bind_twitter_account(accounts(1), "bob_twitter".to_string());
+        let binding = contract.view_twitter_binding(None, Some(accounts(1))).unwrap();
+        assert_eq!(binding.twitter_account, "bob_twitter");
+        assert_eq!(contract.view_twitter_binding(Some("bob_twitter".to_string()), None), Some(binding.clone()));
+
+        let now = binding.bind_time;
+        testing_env!(context.block_timestamp((now + DEFAULT_TWITTER_BINDING_COOLDOWN) * 1000000).build());
+        contract.rebind_twitter_account("bob_twitter".to_string(), accounts(2));
+        assert_eq!(contract.view_twitter_binding(None, Some(accounts(2))).unwrap().twitter_account, "bob_twitter");
+        assert_eq!(contract.view_twitter_binding(None, Some(accounts(1))), None);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.unbind_twitter_account("bob_twitter".to_string());
+        assert_eq!(contract.view_twitter_binding(Some("bob_twitter".to_string()), None), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E046_TWITTER_ACCOUNT_BOUND: this twitter account bob_twitter has been used!")]
+    fn test_bind_used_twitter_account() {
+        let (_, mut contract) = setup_contract();
+        contract.bind_twitter_account(accounts(1), "bob_twitter".to_string());
+        contract.bind_twitter_account(accounts(2), "bob_twitter".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "E047_BINDING_COOLDOWN")]
+    fn test_bind_in_cooldown() {
+        let (_, mut contract) = setup_contract();
+        contract.bind_twitter_account(accounts(1), "bob_twitter".to_string());
+        contract.unbind_twitter_account("bob_twitter".to_string());
+        contract.bind_twitter_account(accounts(2), "bob_twitter".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "E048_TWITTER_ACCOUNT_NOT_BOUND")]
+    fn test_unbind_unbound_twitter_account() {
+        let (_, mut contract) = setup_contract();
+        contract.unbind_twitter_account("bob_twitter".to_string());
+    }
+}
@@ -6,7 +6,7 @@ use itertools::{Itertools, join};
 use near_sdk::{assert_one_yocto, env, near_bindgen};
 use near_sdk::json_types::ValidAccountId;
 use crate::{Account, AccountId, Assets, Contract, CountDownDrawPrize, DrawPrize, MilliTimeStamp, PoolId, PrizeDrawTime, PrizePool};
-use crate::prize::{FtPrize, FtPrizeCreateParam, NftPrize, NftPrizeCreateParam, Prize};
+use crate::prize::{FtPrize, FtPrizeCreateParam, NftPrize, NftPrizeCreateParam, Prize, PrizeId, PrizesParam};
 use crate::prize_pool::{PoolStatus, random_distribution_prizes};
 use crate::StorageKey::TwitterPools;
 use crate::ContractContract;
@@ -15,10 +15,19 @@ use near_sdk::PromiseOrValue::Promise;
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::serde::de::Unexpected::Str;
 use crate::asset::Ft;
-use crate::utils::get_block_milli_time;
+use crate::utils::{get_block_milli_time, log_event};
+use crate::validation::{MAX_PRIZES, prize_count};
+use near_sdk::serde_json::json;
+use crate::merkle::{merkle_leaf, verify_merkle_proof};
+use near_sdk::json_types::Base58CryptoHash;
+use crate::requirement::Requirement;
+use crate::errors::{ContractError, fail};
+use crate::recurrence::{Recurrence, RecurrenceParam};
+use crate::scratch_card::{ScratchCard, ScratchCardParam};
+use crate::stage::{Stage, StageParam, validate_stages};
 
 
-type TwitterAccount = String;
+pub type TwitterAccount = String;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
@@ -37,15 +46,25 @@ pub struct TwitterPoolDisplay {
     pub describe: String,
     pub cover: String,
     pub status: PoolStatus,
+    pub start_time: Option<MilliTimeStamp>,
     pub end_time: MilliTimeStamp,
     pub twitter_link: String,
-    pub requirements: Option<String>,
+    pub requirements: Vec<Requirement>,
     pub prize_type: PrizeType,
+    pub auto_transfer: bool,
+    pub auto_register: bool,
+    pub claim_deadline: Option<MilliTimeStamp>,
+    pub sponsors: Vec<AccountId>,
+    pub scratch_card: Option<ScratchCard>,
+    // chance in basis points that the next scratch card wins
+    pub scratch_odds_bps: Option<u32>,
+    pub referral_count: u32,
 }
 
 
 impl From<TwitterPool> for TwitterPoolDisplay {
-    fn from(pool: TwitterPool) -> Self {
+    fn from(mut pool: TwitterPool) -> Self {
+        pool.refresh_status();
         let prize_type: PrizeType;
         if pool.prize_pool.ft_prizes.len() == 0 && pool.prize_pool.nft_prizes.len() == 0 {
             prize_type = PrizeType::No_Prize
@@ -63,10 +82,18 @@ impl From<TwitterPool> for TwitterPoolDisplay {
             describe: pool.describe,
             cover: pool.cover,
             status: pool.status,
+            start_time: pool.start_time,
             end_time: pool.end_time,
             twitter_link: pool.twitter_link,
             requirements: pool.requirements,
             prize_type,
+            auto_transfer: pool.auto_transfer,
+            auto_register: pool.auto_register,
+            claim_deadline: pool.claim_deadline,
+            sponsors: pool.sponsors,
+            scratch_odds_bps: pool.scratch_card.as_ref().map(ScratchCard::odds_bps),
+            scratch_card: pool.scratch_card,
+            referral_count: pool.referrals.values().sum(),
         }
     }
 }
@@ -107,14 +134,41 @@ pub struct TwitterPool {
     pub cover: String,
     pub prize_pool: PrizePool,
     pub status: PoolStatus,
+    // joining opens at start_time, right after publish when None
+    pub start_time: Option<MilliTimeStamp>,
     pub end_time: MilliTimeStamp,
     pub create_time: MilliTimeStamp,
     pub update_time: MilliTimeStamp,
     pub white_list: HashSet<AccountId>,
-    pub requirements: Option<String>,
+    pub requirements: Vec<Requirement>,
     pub twitter_near_bind: HashMap<TwitterAccount, AccountId>,
     pub twitter_link: String,
-    pub records: Vec<Record>
+    pub records: Vec<Record>,
+    // send prizes to winners' wallets at draw time instead of crediting their assets
+    pub auto_transfer: bool,
+    // register unregistered winners on ft contracts before auto transfer
+    pub auto_register: bool,
+    // creator funded NEAR for those registrations
+    pub storage_budget: Balance,
+    // winners must claim before it, after that the creator reclaims what's left
+    pub claim_deadline: Option<MilliTimeStamp>,
+    // pool that receives the unclaimed prizes instead of the creator
+    pub rollover_pool: Option<PoolId>,
+    pub claimable_prizes: HashMap<AccountId, Assets>,
+    // merkle root over eligible accounts, joiners outside white_list join with a proof
+    pub white_list_root: Option<Base58CryptoHash>,
+    // joiners waiting for their token holdings to be checked
+    pub pending_joins: HashSet<AccountId>,
+    pub recurrence: Option<Recurrence>,
+    // accounts other than the creator that added prizes, in the order they first did
+    pub sponsors: Vec<AccountId>,
+    pub prize_sponsors: HashMap<PrizeId, AccountId>,
+    // joiners learn at once whether they won instead of waiting for the draw
+    pub scratch_card: Option<ScratchCard>,
+    // qualifier draws before the final one at end_time
+    pub stages: Vec<Stage>,
+    // referrals of every referrer, each adds bonus tickets to its weight
+    pub referrals: HashMap<AccountId, u32>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
@@ -134,25 +188,59 @@ pub struct TwitterPoolVO {
 }
 
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(crate = "near_sdk::serde")]
 pub struct TwitterPoolCreateParam {
     pub name: Option<String>,
     pub describe: Option<String>,
     pub cover: Option<String>,
+    pub start_time: Option<MilliTimeStamp>,
     pub end_time: Option<MilliTimeStamp>,
     pub white_list: Option<Vec<AccountId>>,
-    pub requirements: Option<String>,
+    pub requirements: Option<Vec<Requirement>>,
     pub ft_prizes: Option<Vec<FtPrizeCreateParam>>,
     pub nft_prizes: Option<Vec<NftPrizeCreateParam>>,
     pub join_accounts: Option<Vec<AccountId>>,
     pub twitter_link: Option<String>,
+    pub auto_transfer: Option<bool>,
+    pub auto_register: Option<bool>,
+    pub claim_deadline: Option<MilliTimeStamp>,
+    pub rollover_pool: Option<PoolId>,
+    pub white_list_root: Option<Base58CryptoHash>,
+    pub recurrence: Option<RecurrenceParam>,
+    pub scratch_card: Option<ScratchCardParam>,
+    pub stages: Option<Vec<StageParam>>,
 }
 
 impl TwitterPool {
     pub fn publish(&mut self) {
-        assert_ne!(self.end_time, UNINITIALIZED_TIME_STAMP, "end_time haven't init");
-        self.status = PoolStatus::ONGOING;
+        require!(self.end_time != UNINITIALIZED_TIME_STAMP, ContractError::InvalidTimeRange, "end_time haven't init");
+        if let Some(claim_deadline) = self.claim_deadline {
+            require!(claim_deadline > self.end_time, ContractError::InvalidTimeRange, "claim_deadline must be after end_time");
+        }
+        if let Some(start_time) = self.start_time {
+            require!(start_time < self.end_time, ContractError::InvalidTimeRange, "start_time must be before end_time");
+        }
+        validate_stages(&self.stages, self.start_time.unwrap_or(0), self.end_time);
+        self.status = PoolStatus::SCHEDULED;
+        self.refresh_status();
+    }
+
+    /// A scheduled pool becomes ongoing once start_time passes.
+    pub fn refresh_status(&mut self) {
+        if self.status == PoolStatus::SCHEDULED && get_block_milli_time() >= self.start_time.unwrap_or(0) {
+            self.status = PoolStatus::ONGOING;
+        }
+    }
+
+    pub fn assert_joinable(&mut self) {
+        self.refresh_status();
+        let now = get_block_milli_time();
+        if let Some(start_time) = self.start_time {
+            require!(now >= start_time, ContractError::PoolNotStarted, "pool opens at {}", start_time);
+        }
+        require!(self.status == PoolStatus::ONGOING, ContractError::InvalidPoolStatus, "pool can only join in ongoing status");
+        require!(now <= self.join_deadline(), ContractError::PoolEnded, "pool has ended at {}", self.join_deadline());
     }
 }
 
@@ -160,7 +248,7 @@ impl DrawPrize for TwitterPool {
     fn draw_prize(&self) -> HashMap<AccountId, Vec<Prize>> {
         return random_distribution_prizes(&self.prize_pool.ft_prizes,
                                           &self.prize_pool.nft_prizes,
-                                          self.prize_pool.join_accounts.iter().collect_vec(),
+                                          self.eligible_joiners(),
                                           &self.prize_pool.creator_id);
     }
 }
@@ -178,8 +266,10 @@ impl Contract {
     pub fn publish_pool(&mut self, pool_id: PoolId) {
         assert_one_yocto();
         let mut pool = self.internal_get_twitter_pool(&pool_id);
-        assert_eq!(pool.status,PoolStatus::PENDING,"only pool in PENDING state can publish");
+        require!(pool.status == PoolStatus::PENDING, ContractError::InvalidPoolStatus, "only pool in PENDING state can publish");
         pool.publish();
+        self.internal_publish_scratch_card(&mut pool);
+        pool.stages.iter().for_each(|stage| self.pool_queue.push(PrizeDrawTime(pool_id, stage.end_time)));
         self.pool_queue.push(PrizeDrawTime { 0: pool_id.clone(), 1: (&pool).end_time.clone() });
         self.internal_save_twitter_pool(pool);
         log!("{:?}", self.view_prize_pool_queue());
@@ -189,7 +279,7 @@ impl Contract {
     pub(crate) fn internal_creator_use_twitter_pool<F>(&mut self, pool_id: &PoolId, mut f: F)
         where F: FnMut(&mut TwitterPool) {
         let mut pool = self.internal_get_twitter_pool(pool_id);
-        assert_eq!(pool.prize_pool.creator_id, env::predecessor_account_id(), "only creator can use pool!");
+        require!(pool.prize_pool.creator_id == env::predecessor_account_id(), ContractError::NotPoolCreator, "only creator can use pool!");
         f(&mut pool);
         self.internal_save_twitter_pool(pool)
     }
@@ -198,17 +288,17 @@ impl Contract {
     pub(crate) fn internal_creator_remove_twitter_pool<F>(&mut self, pool_id: &PoolId, mut f: F)
         where F: FnMut(&mut TwitterPool) {
         let mut pool = self.internal_get_twitter_pool(pool_id);
-        assert_eq!(pool.prize_pool.creator_id, env::predecessor_account_id(), "only creator can use pool!");
+        require!(pool.prize_pool.creator_id == env::predecessor_account_id(), ContractError::NotPoolCreator, "only creator can use pool!");
         f(&mut pool);
         self.internal_save_twitter_pool(pool)
     }
 
     #[private]
     pub fn internal_get_twitter_pool(&self, id: &PoolId) -> TwitterPool {
-        let pool = self.twitter_prize_pools.get(id).expect("pool not exist");
+        let pool = self.twitter_prize_pools.get(id).unwrap_or_else(|| fail(ContractError::PoolNotFound, format!("pool {} not exist", id)));
         match pool {
             VPool::TwitterPool(prize_pool) => { prize_pool }
-            _ => panic!("error type")
+            _ => fail(ContractError::WrongPoolType, format!("pool {} is not a twitter pool", id))
         }
     }
 
@@ -226,22 +316,38 @@ impl Contract {
             prize_pool: PrizePool {
                 id: pool_id.clone(),
                 creator_id: env::predecessor_account_id(),
-                ft_prizes: param.ft_prizes.as_ref().unwrap_or(&vec![]).iter().map(|e| FtPrize {
-                    ft: Ft { contract_id: e.ft.contract_id.clone(), balance: e.ft.balance },
-                    prize_id: self.next_id(),
-                }).collect_vec(),
-                nft_prizes: param.nft_prizes.as_ref().unwrap_or(&vec![]).iter().map(|e| NftPrize { nft: e.nft.clone(), prize_id: self.next_id() }).collect_vec(),
+                ft_prizes: self.internal_new_ft_prizes(*pool_id, param.ft_prizes.as_ref().unwrap_or(&vec![])),
+                nft_prizes: param.nft_prizes.as_ref().unwrap_or(&vec![]).iter().map(|e| self.internal_new_nft_prize(*pool_id, e.nft.clone())).collect_vec(),
                 join_accounts: HashSet::from_iter(param.join_accounts.as_ref().unwrap_or(&vec![]).iter().map(|e| e.clone())),
+                join_weights: HashMap::new(),
             },
             status: PoolStatus::PENDING,
+            start_time: param.start_time,
             end_time: param.end_time.unwrap_or(UNINITIALIZED_TIME_STAMP),
             create_time: get_block_milli_time(),
             update_time: get_block_milli_time(),
             white_list: param.white_list.as_ref().unwrap_or(&vec![]).iter().map(|e| e.clone()).collect(),
-            requirements: param.requirements.clone(),
+            requirements: param.requirements.clone().unwrap_or_default(),
             twitter_near_bind: Default::default(),
             twitter_link: param.twitter_link.as_ref().unwrap_or(&"".to_string()).clone(),
-            records: vec![]
+            records: vec![],
+            auto_transfer: param.auto_transfer.unwrap_or(false),
+            auto_register: param.auto_register.unwrap_or(false),
+            storage_budget: 0,
+            claim_deadline: param.claim_deadline,
+            rollover_pool: param.rollover_pool,
+            claimable_prizes: Default::default(),
+            white_list_root: param.white_list_root,
+            pending_joins: Default::default(),
+            recurrence: param.recurrence.as_ref().map(|recurrence| Recurrence::new(
+                recurrence,
+                param.ft_prizes.clone().unwrap_or_default(),
+            )),
+            sponsors: vec![],
+            prize_sponsors: Default::default(),
+            scratch_card: param.scratch_card.as_ref().map(ScratchCard::new),
+            stages: param.stages.as_ref().unwrap_or(&vec![]).iter().map_into().collect_vec(),
+            referrals: Default::default(),
         }
     }
 
@@ -251,20 +357,31 @@ impl Contract {
         if param.name.is_some() { pool.name = param.name.as_ref().unwrap().clone(); }
         if param.describe.is_some() { pool.describe = param.describe.as_ref().unwrap().clone(); }
         if param.cover.is_some() { pool.cover = param.cover.as_ref().unwrap().clone(); }
+        // prizes of sponsors stay, only the creator's own are replaced
         if param.ft_prizes.is_some() {
-            pool.prize_pool.ft_prizes = param.ft_prizes.as_ref().unwrap_or(&vec![]).iter().map(|e| FtPrize {
-                ft: Ft { contract_id: e.ft.contract_id.clone(), balance: e.ft.balance },
-                prize_id: self.next_id(),
-            }).collect_vec();
+            let sponsored = pool.prize_pool.ft_prizes.iter().filter(|e| pool.is_sponsored(&e.prize_id)).cloned().collect_vec();
+            pool.prize_pool.ft_prizes.iter().filter(|e| !pool.is_sponsored(&e.prize_id)).for_each(|e| self.internal_refund_prize(&e.prize_id, &pool.prize_pool.creator_id));
+            pool.prize_pool.ft_prizes = self.internal_new_ft_prizes(*pool_id, param.ft_prizes.as_ref().unwrap_or(&vec![]))
+                .into_iter().chain(sponsored).collect_vec();
         }
         if param.nft_prizes.is_some() {
-            pool.prize_pool.nft_prizes = param.nft_prizes.as_ref().unwrap_or(&vec![]).iter().map(|e| NftPrize { nft: e.nft.clone(), prize_id: self.next_id() }).collect_vec();
+            let sponsored = pool.prize_pool.nft_prizes.iter().filter(|e| pool.is_sponsored(&e.prize_id)).cloned().collect_vec();
+            pool.prize_pool.nft_prizes.iter().filter(|e| !pool.is_sponsored(&e.prize_id)).for_each(|e| self.internal_refund_prize(&e.prize_id, &pool.prize_pool.creator_id));
+            pool.prize_pool.nft_prizes = param.nft_prizes.as_ref().unwrap_or(&vec![]).iter()
+                .map(|e| self.internal_new_nft_prize(*pool_id, e.nft.clone()))
+                .chain(sponsored).collect_vec();
         }
 
+        if param.start_time.is_some() { pool.start_time = param.start_time; }
         if param.end_time.is_some() { pool.end_time = param.end_time.as_ref().unwrap().clone(); }
         if param.white_list.is_some() { pool.white_list = param.white_list.as_ref().unwrap_or(&vec![]).iter().map(|e| e.clone()).collect() }
-        if param.requirements.is_some() {pool.requirements = Some(param.requirements.as_ref().unwrap_or(&"{}".to_string()).clone())}
+        if param.requirements.is_some() { pool.requirements = param.requirements.as_ref().unwrap().clone(); }
         if param.twitter_link.is_some() { pool.twitter_link = param.twitter_link.as_ref().unwrap().clone(); }
+        if param.auto_transfer.is_some() { pool.auto_transfer = param.auto_transfer.unwrap(); }
+        if param.auto_register.is_some() { pool.auto_register = param.auto_register.unwrap(); }
+        if param.claim_deadline.is_some() { pool.claim_deadline = param.claim_deadline; }
+        if param.rollover_pool.is_some() { pool.rollover_pool = param.rollover_pool; }
+        if param.white_list_root.is_some() { pool.white_list_root = param.white_list_root; }
         pool.update_time = get_block_milli_time();
         self.internal_save_twitter_pool(pool);
     }
@@ -272,16 +389,29 @@ impl Contract {
     #[payable]
     pub fn create_twitter_pool(&mut self, param: TwitterPoolCreateParam) -> PoolId {
         assert_one_yocto();
+        param.assert_valid(true);
+        param.requirements.as_ref().unwrap_or(&vec![]).iter().for_each(Requirement::validate);
+        require!(param.recurrence.is_none() || param.claim_deadline.is_none(), ContractError::InvalidRecurrence, "recurring pool can't have claim_deadline");
+        // only ft budgets are escrowed per round
+        require!(param.recurrence.is_none() || param.nft_prizes.as_ref().map(|e| e.is_empty()).unwrap_or(true),
+                 ContractError::InvalidRecurrence, "recurring pool can't have nft prizes");
+        require!(param.scratch_card.is_none() || (param.recurrence.is_none() && param.claim_deadline.is_none()),
+                 ContractError::InvalidScratchCard, "scratch card pool can't recur or have claim_deadline");
+        require!(param.stages.is_none() || (param.recurrence.is_none() && param.scratch_card.is_none()),
+                 ContractError::InvalidStages, "pool with stages can't recur or be a scratch card");
         let creator_id = env::predecessor_account_id();
-        let pool_id = self.next_id();
+        let pool_id = self.next_pool_id();
+        // prizes of the later rounds are escrowed up front
+        let rounds = param.recurrence.as_ref().map(|e| e.rounds).unwrap_or(1);
         // todo user should register first
         self.internal_use_account(
             &creator_id,
             |account| {
-                param.ft_prizes.as_ref().unwrap_or(&vec![]).iter().for_each(|x| account.assets.withdraw_contract_amount(&x.ft.contract_id, &x.ft.balance.0));
+                param.ft_prizes.as_ref().unwrap_or(&vec![]).iter().for_each(|x| account.assets.withdraw_contract_amount(&x.ft.contract_id, &(x.ft.balance.0 * rounds as u128)));
                 param.nft_prizes.as_ref().unwrap_or(&vec![]).iter().for_each(|x| account.assets.withdraw_nft(&x.nft));
                 account.pools.insert((&pool_id).clone());
             });
+        self.internal_charge_nft_fee(&creator_id, param.nft_prizes.as_ref().map(|e| e.len()).unwrap_or(0));
 
         // let pool = TwitterPool::new_by_near_call(&param,&creator_id,(self.next_id)(self));
         let pool = self.new_twitter_pool_by_create_param(&pool_id, &param);
@@ -294,31 +424,165 @@ impl Contract {
     pub fn update_twitter_pool(&mut self, param: TwitterPoolCreateParam, pool_id: PoolId) -> PoolId {
         assert_one_yocto();
         let updater = env::predecessor_account_id();
-        let pool = self.internal_get_twitter_pool(&pool_id);
-        assert_eq!(updater, pool.prize_pool.creator_id, "only creator can update!");
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        require!(updater == pool.prize_pool.creator_id, ContractError::NotPoolCreator, "only creator can update!");
+        // nobody can have joined before the pool opens
+        pool.refresh_status();
+        require!(pool.status == PoolStatus::PENDING || pool.status == PoolStatus::SCHEDULED,
+                 ContractError::InvalidPoolStatus, "only pool in PENDING or SCHEDULED state can be updated");
+        // a published pool is queued at the times checked by publish
+        require!(pool.status == PoolStatus::PENDING || (param.start_time.is_none() && param.end_time.is_none() && param.claim_deadline.is_none()),
+                 ContractError::InvalidPoolStatus, "times of a published pool can't be updated");
+        require!(pool.scratch_card.is_none() || pool.status == PoolStatus::PENDING || (param.ft_prizes.is_none() && param.nft_prizes.is_none()),
+                 ContractError::InvalidPoolStatus, "winning slots of scratch card pool are fixed at publish");
+        require!(param.recurrence.is_none(), ContractError::InvalidRecurrence, "recurrence can only be set at creation");
+        require!(param.scratch_card.is_none(), ContractError::InvalidScratchCard, "scratch card can only be set at creation");
+        require!(param.stages.is_none(), ContractError::InvalidStages, "stages can only be set at creation");
+        require!(pool.recurrence.is_none() || param.ft_prizes.is_none(), ContractError::InvalidRecurrence, "ft_prizes of recurring pool can't be updated");
+        param.assert_valid(false);
+        param.requirements.as_ref().unwrap_or(&vec![]).iter().for_each(Requirement::validate);
 
         let mut account = self.internal_get_account(&updater);
 
-        pool.prize_pool.ft_prizes.iter().map(|e| &e.ft).for_each(|ft| account.assets.deposit_ft(ft));
-        pool.prize_pool.nft_prizes.iter().map(|e| &e.nft).for_each(|nft| account.assets.deposit_nft(nft));
+        if param.ft_prizes.is_some() {
+            pool.prize_pool.ft_prizes.iter().filter(|e| !pool.is_sponsored(&e.prize_id)).for_each(|e| account.assets.deposit_ft(&e.ft));
+        }
+        if param.nft_prizes.is_some() {
+            pool.prize_pool.nft_prizes.iter().filter(|e| !pool.is_sponsored(&e.prize_id)).for_each(|e| account.assets.deposit_nft(&e.nft));
+        }
 
         param.ft_prizes.as_ref().unwrap_or(&vec![]).iter().for_each(|x| account.assets.withdraw_contract_amount(&x.ft.contract_id, &x.ft.balance.0));
         param.nft_prizes.as_ref().unwrap_or(&vec![]).iter().for_each(|x| account.assets.withdraw_nft(&x.nft));
+        self.internal_save_account(&updater, account);
+        // nfts kept from the old list already paid the fee
+        let added_nfts = param.nft_prizes.as_ref().unwrap_or(&vec![]).iter()
+            .filter(|x| !pool.prize_pool.nft_prizes.iter().any(|e| e.nft.contract_id == x.nft.contract_id && e.nft.nft_id == x.nft.nft_id && !pool.is_sponsored(&e.prize_id)))
+            .count();
+        self.internal_charge_nft_fee(&updater, added_nfts);
 
         self.update_twitter_pool_by_create_param(&param, &pool_id);
         return pool_id;
     }
 
-    pub fn join_twitter_pool(&mut self, pool_id: u64) {
+    /// Add prizes next to the existing ones, only the new prizes are taken from the creator's assets.
+    #[payable]
+    pub fn add_prizes(&mut self, pool_id: PoolId, prizes: PrizesParam) -> Vec<PrizeId> {
+        assert_one_yocto();
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        let creator_id = env::predecessor_account_id();
+        require!(pool.prize_pool.creator_id == creator_id, ContractError::NotPoolCreator, "only creator can add prizes");
+        let prize_ids = self.internal_add_pool_prizes(&mut pool, &creator_id, &prizes);
+        self.internal_save_twitter_pool(pool);
+        log_event("pool_prizes_added", json!({ "pool_id": pool_id, "prize_ids": prize_ids }));
+        return prize_ids;
+    }
+
+    /// Take prizes out of a pool that isn't published yet, each goes back to the creator's assets.
+    #[payable]
+    pub fn remove_prizes(&mut self, pool_id: PoolId, prize_ids: Vec<PrizeId>) {
+        assert_one_yocto();
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        let creator_id = env::predecessor_account_id();
+        require!(pool.prize_pool.creator_id == creator_id, ContractError::NotPoolCreator, "only creator can remove prizes");
+        require!(pool.status == PoolStatus::PENDING, ContractError::InvalidPoolStatus, "only prizes of pool in PENDING state can be removed");
+        let mut assets = Assets::default();
+        for prize_id in prize_ids.iter() {
+            require!(!pool.is_sponsored(prize_id), ContractError::NoAuthority, "prize {} is funded by a sponsor", prize_id);
+            if let Some(index) = pool.prize_pool.ft_prizes.iter().position(|e| e.prize_id == *prize_id) {
+                require!(pool.recurrence.is_none(), ContractError::InvalidRecurrence, "ft_prizes of recurring pool can't be updated");
+                assets.deposit_ft(&pool.prize_pool.ft_prizes.remove(index).ft);
+                self.internal_refund_prize(prize_id, &creator_id);
+            } else if let Some(index) = pool.prize_pool.nft_prizes.iter().position(|e| e.prize_id == *prize_id) {
+                assets.deposit_nft(&pool.prize_pool.nft_prizes.remove(index).nft);
+                self.internal_refund_prize(prize_id, &creator_id);
+            } else {
+                fail(ContractError::PrizeNotFound, format!("prize {} not exist in pool {}", prize_id, pool_id));
+            }
+        }
+        self.internal_use_account(&creator_id, |account| account.assets.deposit_assets(&assets));
+        pool.update_time = get_block_milli_time();
+        self.internal_save_twitter_pool(pool);
+        log_event("pool_prizes_removed", json!({ "pool_id": pool_id, "prize_ids": prize_ids }));
+    }
+
+    /// Move `prizes` from the funder's assets into a pool that isn't drawn yet, prizes of anyone but
+    /// the creator are remembered as sponsored.
+    pub(crate) fn internal_add_pool_prizes(&mut self, pool: &mut TwitterPool, funder: &AccountId, prizes: &PrizesParam) -> Vec<PrizeId> {
+        pool.refresh_status();
+        // a scheduled pool is published and waits for start_time like an ongoing one
+        require!(pool.status == PoolStatus::PENDING || pool.status == PoolStatus::ONGOING || pool.status == PoolStatus::SCHEDULED,
+                 ContractError::InvalidPoolStatus, "only pool in PENDING or ONGOING state can get prizes");
+        require!(pool.status == PoolStatus::PENDING || get_block_milli_time() < pool.end_time,
+                 ContractError::PoolEnded, "pool has ended at {}", pool.end_time);
+        require!(pool.scratch_card.is_none() || pool.status == PoolStatus::PENDING,
+                 ContractError::InvalidPoolStatus, "winning slots of scratch card pool are fixed at publish");
+        require!(!prizes.ft_prizes.is_empty() || !prizes.nft_prizes.is_empty(), ContractError::ZeroAmount, "no prize to add");
+        require!(pool.recurrence.is_none() || prizes.nft_prizes.is_empty(), ContractError::InvalidRecurrence, "recurring pool can't have nft prizes");
+        TwitterPoolCreateParam {
+            ft_prizes: Some(prizes.ft_prizes.clone()),
+            nft_prizes: Some(prizes.nft_prizes.clone()),
+            ..Default::default()
+        }.assert_valid(false);
+        let prize_count = pool.prize_pool.ft_prizes.len() + pool.prize_pool.nft_prizes.len() + prize_count(&prizes.ft_prizes, &prizes.nft_prizes);
+        require!(prize_count <= MAX_PRIZES, ContractError::TooManyPrizes, "pool can have at most {} prizes", MAX_PRIZES);
+
+        self.internal_use_account(funder, |account| {
+            prizes.ft_prizes.iter().for_each(|x| account.assets.withdraw_ft(&x.ft));
+            prizes.nft_prizes.iter().for_each(|x| account.assets.withdraw_nft(&x.nft));
+        });
+        self.internal_charge_nft_fee(funder, prizes.nft_prizes.len());
+
+        let sponsored = *funder != pool.prize_pool.creator_id;
+        let pool_id = pool.prize_pool.id;
+        let ft_prizes = self.internal_new_ft_prizes(pool_id, &prizes.ft_prizes);
+        let nft_prizes = prizes.nft_prizes.iter().map(|e| self.internal_new_nft_prize(pool_id, e.nft.clone())).collect_vec();
+        let prize_ids = ft_prizes.iter().map(|e| e.prize_id).chain(nft_prizes.iter().map(|e| e.prize_id)).collect_vec();
+        if sponsored {
+            prize_ids.iter().for_each(|prize_id| { pool.prize_sponsors.insert(*prize_id, funder.clone()); });
+        }
+        pool.prize_pool.ft_prizes.extend(ft_prizes);
+        pool.prize_pool.nft_prizes.extend(nft_prizes);
+        pool.update_time = get_block_milli_time();
+        return prize_ids;
+    }
+
+    /// Accounts outside `white_list` join with a merkle `proof` of (account, `weight`) against `white_list_root`.
+    /// Resolves to whether the account joined, pools holding requirements finish the join in a callback.
+    /// A `referrer` that joined before gets bonus tickets in the draw.
+    pub fn join_twitter_pool(&mut self, pool_id: u64, proof: Option<Vec<Base58CryptoHash>>, weight: Option<u32>, referrer: Option<ValidAccountId>) -> PromiseOrValue<bool> {
         let mut pool = self.internal_get_twitter_pool(&pool_id);//self.twitter_prize_pools.get(&pool_id).expect(&format!("no such pool,id:{}", pool_id));
-        assert_eq!(pool.status, PoolStatus::ONGOING, "pool can only join in ongoing status");
+        pool.assert_joinable();
         let joiner = env::predecessor_account_id();
         // check if whitelist contains account
-        assert!(pool.white_list.contains(&joiner), "you are not in whitelist");
+        if !pool.white_list.contains(&joiner) {
+            let root = pool.white_list_root.unwrap_or_else(|| fail(ContractError::NotInWhiteList, "you are not in whitelist"));
+            let proof = proof.unwrap_or_else(|| fail(ContractError::NotInWhiteList, "you are not in whitelist, merkle proof is required"))
+                .into_iter().map_into().collect_vec();
+            require!(verify_merkle_proof(&root.into(), merkle_leaf(&joiner, weight), &proof), ContractError::InvalidMerkleProof, "invalid merkle proof");
+            if let Some(weight) = weight {
+                require!(weight > 0, ContractError::InvalidWeight, "weight must be positive");
+                pool.prize_pool.join_weights.insert(joiner.clone(), weight);
+            }
+        }
+        self.internal_check_and_join_twitter_pool(pool, joiner, referrer.map(|e| e.into()))
+    }
+
+    pub(crate) fn internal_join_twitter_pool(&mut self, mut pool: TwitterPool, joiner: AccountId, referrer: Option<AccountId>) {
+        let pool_id = pool.prize_pool.id;
         self.internal_use_account(
             &joiner,
             |account|{account.pools.insert(pool_id.clone());});
         // self.twitter_prize_pools.insert(&pool.prize_pool.id, &pool);
+        if pool.scratch_card.is_some() {
+            require!(!pool.prize_pool.join_accounts.contains(&joiner), ContractError::AlreadyJoined, "{} has joined the pool", joiner);
+            self.internal_scratch(&mut pool, &joiner);
+            if pool.scratch_card.as_ref().unwrap().is_exhausted() {
+                self.internal_finish_scratch_card(&mut pool);
+            }
+        }
+        if let Some(referrer) = referrer {
+            self.internal_add_referral(&mut pool, &joiner, &referrer);
+        }
         pool.prize_pool.join_accounts.insert(joiner);
         self.internal_save_twitter_pool(pool);
     }
@@ -327,16 +591,22 @@ impl Contract {
     // pub fn unjoin_twitter_pool(&mut self, pool_id: PoolId) {}
 
     pub fn view_twitter_prize_pool(&self, pool_id: PoolId) -> TwitterPool {
-        return self.internal_get_twitter_pool(&pool_id);
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        pool.refresh_status();
+        return pool;
     }
 
     pub fn add_user_into_whitelist(&mut self, param: TwitterPoolWhiteListParam) {
         let mut pool = self.internal_get_twitter_pool(&param.pool_id);
         let signer = env::predecessor_account_id();
         // check authority
-        assert!(signer == pool.prize_pool.creator_id || signer == self.white_list_admin, "no authority change whitelist");
-        assert!(!pool.twitter_near_bind.contains_key(&param.twitter_account),
-                format!("this twitter account {} has been used!", param.twitter_account));
+        require!(signer == pool.prize_pool.creator_id || signer == self.white_list_admin, ContractError::NoAuthority, "no authority change whitelist");
+        // only the white list admin verified the twitter account, so only it binds
+        if signer == self.white_list_admin {
+            self.internal_bind_twitter_account(param.account.as_ref(), &param.twitter_account);
+        } else {
+            self.internal_assert_twitter_binding_free(param.account.as_ref(), &param.twitter_account);
+        }
 
         // todo don't save now for test easier;
         // pool.twitter_near_bind.insert(param.twitter_account,param.account.clone().into());
@@ -345,20 +615,28 @@ impl Contract {
     }
 
     pub fn view_twitter_prize_pool_list(&self) -> Vec<TwitterPoolDisplay> {
-        return self.twitter_prize_pools.values().map(VPool::into_twitter_pool).map_into().collect_vec();
+        return self.twitter_prize_pools.values().filter_map(VPool::twitter_pool).map_into().collect_vec();
         // return self.twitter_prize_pools.get(&pool_id).expect("inexistent pool id");
     }
 
+    /// Cancel a pool that isn't drawn yet, the prizes still in escrow go back to their funders.
     pub fn delete_twitter_prize_pool(&mut self, pool_id: PoolId) {
-        let pool = self.internal_get_twitter_pool(&pool_id);
-        assert_eq!(pool.prize_pool.creator_id, env::predecessor_account_id(), "only creator can remove pool");
-
-        self.internal_use_account(&env::predecessor_account_id(), |account| {
-            pool.prize_pool.ft_prizes.iter().for_each(|ft| account.assets.deposit_ft(&ft.ft));
-            pool.prize_pool.nft_prizes.iter().for_each(|nft| account.assets.deposit_nft(&nft.nft));
-        });
+        let mut pool = self.internal_get_twitter_pool(&pool_id);
+        require!(pool.prize_pool.creator_id == env::predecessor_account_id(), ContractError::NotPoolCreator, "only creator can remove pool");
+        pool.refresh_status();
+        require!(pool.status == PoolStatus::PENDING || pool.status == PoolStatus::SCHEDULED || pool.status == PoolStatus::ONGOING,
+                 ContractError::InvalidPoolStatus, "only pool in PENDING, SCHEDULED or ONGOING state can be removed");
+
+        self.internal_refund_pool_prizes(&pool);
+        if let Some(recurrence) = pool.recurrence.as_ref() {
+            self.internal_use_account(&env::predecessor_account_id(), |account| account.assets.deposit_assets(&recurrence.escrow));
+        }
 
         self.twitter_prize_pools.remove(&pool_id);
+        self.pool_queue.retain(|e| e.0 != pool_id);
+        if pool.storage_budget > 0 {
+            near_sdk::Promise::new(pool.prize_pool.creator_id.clone()).transfer(pool.storage_budget);
+        }
     }
 }
 
@@ -370,31 +648,36 @@ mod test_twitter {
     use near_sdk::log;
     use crate::*;
     use crate::asset::Ft;
-    use crate::prize::FtPrizeCreateParam;
+    use crate::prize::{FtPrizeCreateParam, PrizesParam};
     use crate::TwitterPool;
     use crate::tests::setup_contract;
     use near_sdk::test_utils::{accounts, VMContextBuilder};
     use near_sdk_sim::lazy_static_include::syn::export::str;
     use crate::twitter_giveaway::TwitterPoolCreateParam;
+    use crate::merkle::{merkle_leaf, merkle_parent};
+    use crate::prize_pool::PoolStatus;
+    use near_sdk::{testing_env, MockedBlockchain};
 
     #[test]
     fn test_create_param() {
         let param = TwitterPoolCreateParam {
-            name: None,
+            name: Some("pool".to_string()),
             describe: None,
             cover: None,
-            end_time: Some(1),
+            end_time: Some(2000000),
             white_list: None,
             requirements: None,
             ft_prizes: Some(vec![FtPrizeCreateParam {
                 ft: Ft {
                     contract_id: "wrap.testnet".to_string(),
                     balance: U128::from(1000000000000000000000000)
-                }
+                },
+                winners: None
             }]),
             nft_prizes: None,
             join_accounts: None,
             twitter_link: None,
+            ..Default::default()
         };
         let (mut context, mut contract) = setup_contract();
         contract.internal_deposit_ft(accounts(0).as_ref(),&"wrap.testnet".to_string(),&U128::from(1000000000000000000000000));
@@ -410,6 +693,8 @@ mod test_twitter {
     #[test]
     fn test_create() {
         const CREATE_PARAM_RAW: &str = r#"{
+"name": "pool",
+"end_time": 2000000,
 "ft_prizes": [
       {
         "ft": {
@@ -431,4 +716,193 @@ mod test_twitter {
         contract.publish_pool(pool_id);
         println!("{:?}", contract.view_prize_pool_queue())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_join_with_merkle_proof() {
+        let (mut context, mut contract) = setup_contract();
+        let bob_leaf = merkle_leaf(&accounts(1).into(), Some(3));
+        let charlie_leaf = merkle_leaf(&accounts(2).into(), None);
+        let param = TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(2000000),
+            white_list_root: Some(merkle_parent(&bob_leaf, &charlie_leaf).into()),
+            ..Default::default()
+        };
+        let pool_id = contract.create_twitter_pool(param);
+        contract.publish_pool(pool_id);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, Some(vec![charlie_leaf.into()]), Some(3), None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.join_twitter_pool(pool_id, Some(vec![bob_leaf.into()]), None, None);
+
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.prize_pool.join_accounts.len(), 2);
+        assert_eq!(pool.prize_pool.weight_of(&accounts(1).into()), 3);
+        assert_eq!(pool.prize_pool.weight_of(&accounts(2).into()), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "E021_INVALID_MERKLE_PROOF")]
+    fn test_join_with_wrong_weight() {
+        let (mut context, mut contract) = setup_contract();
+        let bob_leaf = merkle_leaf(&accounts(1).into(), Some(3));
+        let charlie_leaf = merkle_leaf(&accounts(2).into(), None);
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(2000000),
+            white_list_root: Some(merkle_parent(&bob_leaf, &charlie_leaf).into()),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, Some(vec![charlie_leaf.into()]), Some(30), None);
+    }
+
+    #[test]
+    fn test_scheduled_pool() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            start_time: Some(1700000),
+            end_time: Some(2000000),
+            white_list: Some(vec![accounts(1).into()]),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+        assert_eq!(contract.view_twitter_prize_pool(pool_id).status, PoolStatus::SCHEDULED);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(1700000 * 1000000).build());
+        assert_eq!(contract.view_twitter_prize_pool(pool_id).status, PoolStatus::ONGOING);
+        contract.join_twitter_pool(pool_id, None, None, None);
+        assert!(contract.view_twitter_prize_pool(pool_id).prize_pool.join_accounts.contains(accounts(1).as_ref()));
+    }
+
+    #[test]
+    #[should_panic(expected = "E005_POOL_NOT_STARTED: pool opens at 1700000")]
+    fn test_join_before_start_time() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            start_time: Some(1700000),
+            end_time: Some(2000000),
+            white_list: Some(vec![accounts(1).into()]),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E006_POOL_ENDED: pool has ended at 2000000")]
+    fn test_join_after_end_time() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(2000000),
+            white_list: Some(vec![accounts(1).into()]),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(2000001 * 1000000).build());
+        contract.join_twitter_pool(pool_id, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E001_POOL_NOT_FOUND")]
+    fn test_join_missing_pool() {
+        let (_, mut contract) = setup_contract();
+        contract.join_twitter_pool(42, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E010_INSUFFICIENT_BALANCE")]
+    fn test_create_without_prize_balance() {
+        let (_, mut contract) = setup_contract();
+        contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            ft_prizes: Some(vec![FtPrizeCreateParam { ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(100) }, winners: None }]),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_add_and_remove_prizes() {
+        let (_, mut contract) = setup_contract();
+        contract.internal_deposit_ft(accounts(0).as_ref(), &"wrap.testnet".to_string(), &U128(300));
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            ft_prizes: Some(vec![FtPrizeCreateParam { ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(100) }, winners: None }]),
+            ..Default::default()
+        });
+        let first_id = contract.view_twitter_prize_pool(pool_id).prize_pool.ft_prizes[0].prize_id;
+        let added = contract.add_prizes(pool_id, PrizesParam {
+            ft_prizes: vec![FtPrizeCreateParam { ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(150) }, winners: None }],
+            nft_prizes: vec![],
+        });
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 50);
+
+        contract.remove_prizes(pool_id, vec![first_id]);
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.prize_pool.ft_prizes.iter().map(|e| e.prize_id).collect::<Vec<_>>(), added);
+        assert_eq!(contract.view_account_balance(accounts(0)).get("wrap.testnet").unwrap().0, 150);
+    }
+
+    #[test]
+    #[should_panic(expected = "E004_INVALID_POOL_STATUS")]
+    fn test_update_ongoing_pool() {
+        let (_, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(2000000),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+        contract.update_twitter_pool(TwitterPoolCreateParam { name: Some("renamed".to_string()), ..Default::default() }, pool_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "E004_INVALID_POOL_STATUS: times of a published pool can't be updated")]
+    fn test_update_scheduled_pool_end_time() {
+        let (_, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            start_time: Some(1700000),
+            end_time: Some(2000000),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+        contract.update_twitter_pool(TwitterPoolCreateParam { end_time: Some(3000000), ..Default::default() }, pool_id);
+    }
+
+    #[test]
+    fn test_update_scheduled_pool_then_draw() {
+        let (mut context, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            start_time: Some(1700000),
+            end_time: Some(2000000),
+            ..Default::default()
+        });
+        contract.publish_pool(pool_id);
+        contract.update_twitter_pool(TwitterPoolCreateParam { name: Some("renamed".to_string()), ..Default::default() }, pool_id);
+
+        // the queued draw time still matches the pool
+        testing_env!(context.block_timestamp(2000000 * 1000000).build());
+        contract.pools_prize_draw();
+        let pool = contract.view_twitter_prize_pool(pool_id);
+        assert_eq!(pool.name, "renamed");
+        assert_eq!(pool.status, PoolStatus::FINISHED);
+        assert_eq!(contract.view_prize_pool_queue_len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "E009_PRIZE_NOT_FOUND")]
+    fn test_remove_missing_prize() {
+        let (_, mut contract) = setup_contract();
+        let pool_id = contract.create_twitter_pool(TwitterPoolCreateParam { name: Some("pool".to_string()), ..Default::default() });
+        contract.remove_prizes(pool_id, vec![42]);
+    }
+}
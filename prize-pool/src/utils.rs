@@ -1,6 +1,8 @@
 use std::convert::TryInto;
 use near_sdk::json_types::U128;
 use near_sdk::{ext_contract, AccountId, Balance, Gas, env, Timestamp};
+use near_sdk::log;
+use near_sdk::serde_json::{json, Value};
 use crate::MilliTimeStamp;
 use crate::prize::{FtPrize, NftPrize};
 
@@ -25,14 +27,40 @@ pub fn random_number_from_block() -> u64{
     return seed_num;
 }
 
+/// `env::random_seed` is the same for every call of a block, mixing in a `nonce` unique to the
+/// call (the pool, the sale, the buyer) gives each call of the block its own random number.
+pub fn random_number_with_nonce(nonce: &[u8]) -> u64 {
+    let mut input = env::random_seed();
+    input.extend_from_slice(nonce);
+    let hash = env::sha256(&input);
+    let mut arr: [u8; 8] = Default::default();
+    arr.copy_from_slice(&hash[..8]);
+    return u64::from_le_bytes(arr);
+}
+
 pub fn vec_random<T>(vec: &mut Vec<T>)-> Option<T> {
+    return vec_random_with_seed(vec, random_number_from_block());
+}
+
+pub fn vec_random_with_seed<T>(vec: &mut Vec<T>, seed: u64)-> Option<T> {
     let len = vec.len();
     if len==0 {return Option::None};
-    let choose_index: usize = (random_number_from_block() % (len as u64)) as usize;
+    let choose_index: usize = (seed % (len as u64)) as usize;
     vec.swap(len-1, choose_index.into());
     vec.pop()
 }
 
+/// Pick and remove one element, the chance of each element is proportional to its weight.
+pub fn weighted_vec_random<T>(vec: &mut Vec<(T, u64)>) -> Option<T> {
+    let total: u64 = vec.iter().map(|e| e.1).sum();
+    if total == 0 { return Option::None };
+    let mut point = random_number_from_block() % total;
+    let choose_index = vec.iter()
+        .position(|e| if point < e.1 { true } else { point -= e.1; false })
+        .unwrap();
+    Some(vec.swap_remove(choose_index).0)
+}
+
 /// Attach no deposit.
 pub const NO_DEPOSIT: u128 = 0;
 /// hotfix_insuffient_gas_for_mft_resolve_transfer, increase from 5T to 20T
@@ -43,6 +71,40 @@ pub const GAS_FOR_FT_TRANSFER_CALL: Gas = 25_000_000_000_000 + GAS_FOR_RESOLVE_T
 /// Amount of gas for fungible token transfers, increased to 20T to support AS token contracts.
 pub const GAS_FOR_FT_TRANSFER: Gas = 20_000_000_000_000;
 
+/// Gas kept aside when pushing prizes out during a draw, so the draw itself can still finish.
+pub const GAS_RESERVED_FOR_DRAW: Gas = 15_000_000_000_000;
+
+pub const GAS_FOR_STORAGE_BALANCE_OF: Gas = 10_000_000_000_000;
+
+pub const GAS_FOR_STORAGE_DEPOSIT: Gas = 20_000_000_000_000;
+
+/// storage_deposit_callback starts the ft transfer and its resolve.
+pub const GAS_FOR_STORAGE_DEPOSIT_CALLBACK: Gas = 10_000_000_000_000 + GAS_FOR_FT_TRANSFER + GAS_FOR_RESOLVE_TRANSFER;
+
+/// storage_balance_of_callback may start storage_deposit and its callback.
+pub const GAS_FOR_REGISTER_CALLBACK: Gas = 10_000_000_000_000 + GAS_FOR_STORAGE_DEPOSIT + GAS_FOR_STORAGE_DEPOSIT_CALLBACK;
+
+/// deposit_and_stake, unstake and withdraw of a staking pool.
+pub const GAS_FOR_STAKING_ACTION: Gas = 50_000_000_000_000;
+
+pub const GAS_FOR_STAKING_CALLBACK: Gas = 10_000_000_000_000;
+
+pub const GAS_FOR_GET_STAKED_BALANCE: Gas = 10_000_000_000_000;
+
+/// on_staking_lottery_balance may unstake the reward of the round for the winner.
+pub const GAS_FOR_STAKING_DRAW_CALLBACK: Gas = 20_000_000_000_000 + GAS_FOR_STAKING_ACTION + GAS_FOR_STAKING_CALLBACK;
+
+pub const GAS_FOR_TOKEN_GATE_QUERY: Gas = 10_000_000_000_000;
+
+pub const GAS_FOR_TOKEN_GATE_CALLBACK: Gas = 20_000_000_000_000;
+
+/// NEP-145 registration deposit of a standard ft contract, token contracts refund what they don't use.
+pub const STORAGE_DEPOSIT_FOR_FT_REGISTRATION: Balance = 1_250_000_000_000_000_000_000;
+
+/// Max number of token transfers a single batch withdrawal may dispatch,
+/// every transfer costs GAS_FOR_FT_TRANSFER.
+pub const MAX_BATCH_WITHDRAW_TRANSFERS: usize = 10;
+
 /// Fee divisor, allowing to provide fee in bps.
 pub const FEE_DIVISOR: u32 = 10_000;
 
@@ -51,4 +113,33 @@ pub const INIT_SHARES_SUPPLY: u128 = 1_000_000_000_000_000_000_000_000;
 
 pub fn get_block_milli_time() -> MilliTimeStamp {
     return env::block_timestamp()/1000000;
-}
\ No newline at end of file
+}
+
+/// Whether the current call still has enough gas to start one transfer with its callback.
+pub fn has_gas_for_transfer() -> bool {
+    return has_gas_for(GAS_FOR_FT_TRANSFER + GAS_FOR_RESOLVE_TRANSFER);
+}
+
+/// Whether the current call still has enough gas to register the receiver before the transfer.
+pub fn has_gas_for_register_and_transfer() -> bool {
+    return has_gas_for(GAS_FOR_STORAGE_BALANCE_OF + GAS_FOR_REGISTER_CALLBACK);
+}
+
+/// Whether the current call still has enough gas to start the draw of a staking lottery.
+pub fn has_gas_for_staking_draw() -> bool {
+    return has_gas_for(GAS_FOR_GET_STAKED_BALANCE + GAS_FOR_STAKING_DRAW_CALLBACK);
+}
+
+fn has_gas_for(gas: Gas) -> bool {
+    return env::prepaid_gas() - env::used_gas() > gas + GAS_RESERVED_FOR_DRAW;
+}
+
+/// Log an event in the NEP-297 format so indexers can pick it up.
+pub fn log_event(event: &str, data: Value) {
+    log!("EVENT_JSON:{}", json!({
+        "standard": "superise",
+        "version": "1.0.0",
+        "event": event,
+        "data": [data],
+    }));
+}
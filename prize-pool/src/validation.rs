@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use std::fmt;
+use crate::*;
+use crate::errors::{ContractError, fail};
+use crate::prize::{FtPrizeCreateParam, NftPrizeCreateParam};
+use crate::twitter_giveaway::TwitterPoolCreateParam;
+use crate::utils::get_block_milli_time;
+
+pub const MAX_NAME_LENGTH: usize = 64;
+pub const MAX_DESCRIBE_LENGTH: usize = 1024;
+pub const MAX_COVER_LENGTH: usize = 512;
+pub const MAX_TWITTER_LINK_LENGTH: usize = 256;
+// ft and nft prizes together, every prize costs a transfer at draw time
+pub const MAX_PRIZES: usize = 100;
+pub const MIN_POOL_DURATION: MilliTimeStamp = 60 * 1000;
+pub const MAX_POOL_DURATION: MilliTimeStamp = 365 * 24 * 60 * 60 * 1000;
+
+#[derive(Debug, PartialEq)]
+pub enum PoolParamError {
+    EmptyName,
+    TooLong { field: &'static str, max: usize },
+    EndTimeInPast { end_time: MilliTimeStamp },
+    StartAfterEnd { start_time: MilliTimeStamp, end_time: MilliTimeStamp },
+    DurationTooShort { min: MilliTimeStamp },
+    DurationTooLong { max: MilliTimeStamp },
+    ZeroAmount { contract_id: String },
+    EmptyNftId { contract_id: String },
+    DuplicateNft { contract_id: String, nft_id: String },
+    InvalidAccountId { account_id: String },
+    TooManyPrizes { max: usize },
+    InvalidWinners { contract_id: String, winners: u32 },
+}
+
+impl fmt::Display for PoolParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolParamError::EmptyName => write!(f, "name of pool is empty"),
+            PoolParamError::TooLong { field, max } => write!(f, "{} of pool is longer than {}", field, max),
+            PoolParamError::EndTimeInPast { end_time } => write!(f, "end_time {} is in the past", end_time),
+            PoolParamError::StartAfterEnd { start_time, end_time } => write!(f, "start_time {} must be before end_time {}", start_time, end_time),
+            PoolParamError::DurationTooShort { min } => write!(f, "pool must last at least {} ms", min),
+            PoolParamError::DurationTooLong { max } => write!(f, "pool can last at most {} ms", max),
+            PoolParamError::ZeroAmount { contract_id } => write!(f, "ft prize of {} must be positive", contract_id),
+            PoolParamError::EmptyNftId { contract_id } => write!(f, "nft prize of {} has no nft_id", contract_id),
+            PoolParamError::DuplicateNft { contract_id, nft_id } => write!(f, "nft prize {}:{} is duplicated", contract_id, nft_id),
+            PoolParamError::InvalidAccountId { account_id } => write!(f, "invalid account id: {}", account_id),
+            PoolParamError::TooManyPrizes { max } => write!(f, "pool can have at most {} prizes", max),
+            PoolParamError::InvalidWinners { contract_id, winners } => write!(f, "ft prize of {} can't be shared among {} winners", contract_id, winners),
+        }
+    }
+}
+
+impl PoolParamError {
+    pub fn error(&self) -> ContractError {
+        match self {
+            PoolParamError::EmptyName => ContractError::EmptyName,
+            PoolParamError::TooLong { .. } => ContractError::FieldTooLong,
+            PoolParamError::EndTimeInPast { .. }
+            | PoolParamError::StartAfterEnd { .. }
+            | PoolParamError::DurationTooShort { .. }
+            | PoolParamError::DurationTooLong { .. } => ContractError::InvalidTimeRange,
+            PoolParamError::ZeroAmount { .. } => ContractError::ZeroAmount,
+            PoolParamError::EmptyNftId { .. } | PoolParamError::DuplicateNft { .. } => ContractError::InvalidNftPrize,
+            PoolParamError::InvalidAccountId { .. } => ContractError::InvalidAccountId,
+            PoolParamError::TooManyPrizes { .. } => ContractError::TooManyPrizes,
+            PoolParamError::InvalidWinners { .. } => ContractError::InvalidWinners,
+        }
+    }
+}
+
+/// Every share of a shared ft prize counts as a prize.
+pub fn prize_count(ft_prizes: &[FtPrizeCreateParam], nft_prizes: &[NftPrizeCreateParam]) -> usize {
+    ft_prizes.iter().map(|e| e.winner_count() as usize).sum::<usize>() + nft_prizes.len()
+}
+
+fn check_length(field: &'static str, value: &Option<String>, max: usize) -> Result<(), PoolParamError> {
+    match value {
+        Some(value) if value.chars().count() > max => Err(PoolParamError::TooLong { field, max }),
+        _ => Ok(()),
+    }
+}
+
+fn check_account_id(account_id: &str) -> Result<(), PoolParamError> {
+    if env::is_valid_account_id(account_id.as_bytes()) {
+        Ok(())
+    } else {
+        Err(PoolParamError::InvalidAccountId { account_id: account_id.to_string() })
+    }
+}
+
+/// Check the name and the texts that are set, `creating` requires the name.
+pub fn validate_info(name: &Option<String>, describe: &Option<String>, cover: &Option<String>, creating: bool) -> Result<(), PoolParamError> {
+    if name.as_ref().map(|e| e.trim().is_empty()).unwrap_or(creating) {
+        return Err(PoolParamError::EmptyName);
+    }
+    check_length("name", name, MAX_NAME_LENGTH)?;
+    check_length("describe", describe, MAX_DESCRIBE_LENGTH)?;
+    check_length("cover", cover, MAX_COVER_LENGTH)
+}
+
+/// Prizes a creator escrows into a pool of any kind.
+pub fn validate_prizes(ft_prizes: &[FtPrizeCreateParam], nft_prizes: &[NftPrizeCreateParam]) -> Result<(), PoolParamError> {
+    if prize_count(ft_prizes, nft_prizes) > MAX_PRIZES {
+        return Err(PoolParamError::TooManyPrizes { max: MAX_PRIZES });
+    }
+    for prize in ft_prizes {
+        check_account_id(&prize.ft.contract_id)?;
+        if prize.ft.balance.0 == 0 {
+            return Err(PoolParamError::ZeroAmount { contract_id: prize.ft.contract_id.clone() });
+        }
+        // every winner gets a non-zero share
+        if prize.winner_count() == 0 || prize.ft.balance.0 < prize.winner_count() as u128 {
+            return Err(PoolParamError::InvalidWinners { contract_id: prize.ft.contract_id.clone(), winners: prize.winner_count() });
+        }
+    }
+    let mut nfts = HashSet::new();
+    for prize in nft_prizes {
+        check_account_id(&prize.nft.contract_id)?;
+        if prize.nft.nft_id.is_empty() {
+            return Err(PoolParamError::EmptyNftId { contract_id: prize.nft.contract_id.clone() });
+        }
+        if !nfts.insert((&prize.nft.contract_id, &prize.nft.nft_id)) {
+            return Err(PoolParamError::DuplicateNft {
+                contract_id: prize.nft.contract_id.clone(),
+                nft_id: prize.nft.nft_id.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn assert_valid_param(result: Result<(), PoolParamError>) {
+    if let Err(err) = result {
+        fail(err.error(), err);
+    }
+}
+
+impl TwitterPoolCreateParam {
+    /// Check the fields that are set, `creating` also requires the ones a new pool can't miss.
+    pub fn validate(&self, creating: bool) -> Result<(), PoolParamError> {
+        validate_info(&self.name, &self.describe, &self.cover, creating)?;
+        check_length("twitter_link", &self.twitter_link, MAX_TWITTER_LINK_LENGTH)?;
+
+        if let Some(end_time) = self.end_time {
+            let now = get_block_milli_time();
+            if end_time <= now {
+                return Err(PoolParamError::EndTimeInPast { end_time });
+            }
+            let start_time = self.start_time.unwrap_or(now).max(now);
+            if start_time >= end_time {
+                return Err(PoolParamError::StartAfterEnd { start_time, end_time });
+            }
+            if end_time - start_time < MIN_POOL_DURATION {
+                return Err(PoolParamError::DurationTooShort { min: MIN_POOL_DURATION });
+            }
+            if end_time - start_time > MAX_POOL_DURATION {
+                return Err(PoolParamError::DurationTooLong { max: MAX_POOL_DURATION });
+            }
+        }
+
+        let ft_prizes = self.ft_prizes.as_ref().map(|e| e.as_slice()).unwrap_or(&[]);
+        let nft_prizes = self.nft_prizes.as_ref().map(|e| e.as_slice()).unwrap_or(&[]);
+        validate_prizes(ft_prizes, nft_prizes)?;
+
+        self.white_list.iter().chain(self.join_accounts.iter()).flatten()
+            .try_for_each(|account_id| check_account_id(account_id))
+    }
+
+    pub fn assert_valid(&self, creating: bool) {
+        assert_valid_param(self.validate(creating));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_validation {
+    use near_sdk::test_utils::accounts;
+    use crate::*;
+    use crate::asset::{Ft, Nft};
+    use crate::prize::{FtPrizeCreateParam, NftPrizeCreateParam};
+    use crate::tests::setup_contract;
+    use crate::twitter_giveaway::TwitterPoolCreateParam;
+    use crate::validation::{MAX_POOL_DURATION, PoolParamError};
+
+    fn param() -> TwitterPoolCreateParam {
+        TwitterPoolCreateParam {
+            name: Some("pool".to_string()),
+            end_time: Some(2000000),
+            ..Default::default()
+        }
+    }
+
+    fn nft(nft_id: &str) -> NftPrizeCreateParam {
+        NftPrizeCreateParam { nft: Nft { contract_id: "nft.testnet".to_string(), nft_id: nft_id.to_string() } }
+    }
+
+    #[test]
+    fn test_validate() {
+        let (_, _contract) = setup_contract();
+        assert_eq!(param().validate(true), Ok(()));
+        assert_eq!(TwitterPoolCreateParam { name: None, ..param() }.validate(true), Err(PoolParamError::EmptyName));
+        // an update leaves the name alone
+        assert_eq!(TwitterPoolCreateParam { name: None, ..param() }.validate(false), Ok(()));
+        assert_eq!(TwitterPoolCreateParam { name: Some("a".repeat(65)), ..param() }.validate(true),
+                   Err(PoolParamError::TooLong { field: "name", max: 64 }));
+        assert_eq!(TwitterPoolCreateParam { end_time: Some(1), ..param() }.validate(true),
+                   Err(PoolParamError::EndTimeInPast { end_time: 1 }));
+        assert_eq!(TwitterPoolCreateParam { end_time: Some(1638800), ..param() }.validate(true),
+                   Err(PoolParamError::DurationTooShort { min: 60000 }));
+        assert_eq!(TwitterPoolCreateParam { end_time: Some(2000000 + MAX_POOL_DURATION), ..param() }.validate(true),
+                   Err(PoolParamError::DurationTooLong { max: MAX_POOL_DURATION }));
+        assert_eq!(TwitterPoolCreateParam {
+            ft_prizes: Some(vec![FtPrizeCreateParam { ft: Ft { contract_id: "wrap.testnet".to_string(), balance: U128(0) }, winners: None }]),
+            ..param()
+        }.validate(true), Err(PoolParamError::ZeroAmount { contract_id: "wrap.testnet".to_string() }));
+        assert_eq!(TwitterPoolCreateParam { nft_prizes: Some(vec![nft("1"), nft("1")]), ..param() }.validate(true),
+                   Err(PoolParamError::DuplicateNft { contract_id: "nft.testnet".to_string(), nft_id: "1".to_string() }));
+        assert_eq!(TwitterPoolCreateParam { white_list: Some(vec![accounts(1).into(), "Bob!".to_string()]), ..param() }.validate(true),
+                   Err(PoolParamError::InvalidAccountId { account_id: "Bob!".to_string() }));
+        assert_eq!(TwitterPoolCreateParam { nft_prizes: Some((0..101).map(|e| nft(&e.to_string())).collect()), ..param() }.validate(true),
+                   Err(PoolParamError::TooManyPrizes { max: 100 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "E030_EMPTY_NAME: name of pool is empty")]
+    fn test_create_without_name() {
+        let (_, mut contract) = setup_contract();
+        contract.create_twitter_pool(TwitterPoolCreateParam { name: Some(" ".to_string()), ..param() });
+    }
+}
@@ -4,6 +4,7 @@ use near_sdk_sim::{
 };
 use prize_pool::{ContractContract as PrizePoolContract};
 use test_token::{ContractContract as TestTokenContract};
+use mock_staking_pool::{ContractContract as MockStakingPoolContract};
 use near_sdk::json_types::{ValidAccountId, U128};
 use std::convert::TryFrom;
 use crate::common::constant::PRIZE_POOL_ACCOUNT_ID;
@@ -14,6 +15,7 @@ use crate::common::utils::to_va;
 near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
     PRIZPOOL_WASM_BYTES => "./out/prize-pool.wasm",
     TEST_TOKEN_WASM_BYTES => "../res/test_token.wasm",
+    MOCK_STAKING_POOL_WASM_BYTES => "../res/mock_staking_pool.wasm",
 }
 
 
@@ -63,4 +65,17 @@ pub fn deploy_test_token_contract(
             .assert_success();
     }
     t
+}
+
+pub fn deploy_mock_staking_pool_contract(
+    signer_account: &UserAccount,
+    staking_pool_id: AccountId,
+)->ContractAccount<MockStakingPoolContract> {
+    deploy!(
+        contract: MockStakingPoolContract,
+        contract_id: staking_pool_id,
+        bytes: &MOCK_STAKING_POOL_WASM_BYTES,
+        signer_account: signer_account,
+        init_method: new()
+    )
 }
\ No newline at end of file
@@ -0,0 +1,61 @@
+use near_sdk::json_types::U128;
+use near_sdk_sim::{call, init_simulator, to_yocto, view};
+use prize_pool::prize_pool::PoolId;
+use prize_pool::staking_lottery::{StakingDeposit, StakingLottery, StakingLotteryCreateParam};
+use crate::common::contracts::{deploy_mock_staking_pool_contract, deploy_prize_pool_contract};
+use crate::common::utils::*;
+pub mod common;
+
+
+#[test]
+fn test_staking_lottery() {
+    let root = init_simulator(None);
+    let user = root.create_user("user".to_string(), to_yocto("100"));
+    let prize_pool_contract = deploy_prize_pool_contract(&root, None);
+    let staking_pool = deploy_mock_staking_pool_contract(&root, "validator".to_string());
+
+    let pool_id = call!(
+        root,
+        prize_pool_contract.create_staking_lottery(StakingLotteryCreateParam {
+            name: None,
+            describe: None,
+            cover: None,
+            staking_pool_id: to_va(staking_pool.account_id()),
+            round_interval: 10_000,
+        }),
+        deposit = 1
+    ).unwrap_json::<PoolId>();
+    call!(
+        user,
+        prize_pool_contract.deposit_to_staking_lottery(pool_id),
+        deposit = to_yocto("10")
+    ).assert_success();
+    let staked = view!(staking_pool.get_account_staked_balance(to_va(prize_pool_contract.account_id()))).unwrap_json::<U128>();
+    assert_eq!(staked.0, to_yocto("10"));
+
+    // rewards of the round
+    call!(
+        root,
+        staking_pool.add_reward(to_va(prize_pool_contract.account_id())),
+        deposit = to_yocto("1")
+    ).assert_success();
+    root.borrow_runtime_mut().produce_blocks(20).unwrap();
+    call!(root, prize_pool_contract.pools_prize_draw()).assert_success();
+
+    let lottery = view!(prize_pool_contract.view_staking_lottery(pool_id)).unwrap_json::<StakingLottery>();
+    assert_eq!(lottery.round, 2);
+    assert_eq!(lottery.records[0].receiver, user.account_id());
+    let deposit = view!(prize_pool_contract.view_staking_deposit(pool_id, to_va(user.account_id()))).unwrap_json::<Option<StakingDeposit>>().unwrap();
+    assert_eq!(deposit.amount.0, to_yocto("10"));
+    assert_eq!(deposit.unstaked.0, to_yocto("1"));
+
+    // principal stays withdrawable
+    call!(user, prize_pool_contract.withdraw_from_staking_lottery(pool_id, U128(to_yocto("10"))), deposit = 1).assert_success();
+    let staked = view!(staking_pool.get_account_staked_balance(to_va(prize_pool_contract.account_id()))).unwrap_json::<U128>();
+    assert_eq!(staked.0, 0);
+
+    root.borrow_runtime_mut().produce_blocks(20).unwrap();
+    let balance = user.account().unwrap().amount;
+    call!(user, prize_pool_contract.claim_from_staking_lottery(pool_id), deposit = 1).assert_success();
+    assert!(user.account().unwrap().amount > balance + to_yocto("10.9"));
+}